@@ -5,51 +5,72 @@ pub enum AlgoResult {
     None,
 }
 
-pub type Algo = for<'a> fn(Size, &'a Vec<Block>) -> AlgoResult;
+pub type Algo = for<'a> fn(Size, &'a [Block]) -> AlgoResult;
 
-pub fn first_fit(size: Size, blocks: &Vec<Block>) -> AlgoResult {
-    for (i, block) in blocks.iter().enumerate() {
-        if block.size >= size {
+pub fn first_fit(size: Size, blocks: &[Block]) -> AlgoResult {
+    first_fit_in(size, blocks.iter().map(|b| b.size))
+}
+
+pub fn best_fit(size: Size, blocks: &[Block]) -> AlgoResult {
+    best_fit_in(size, blocks.iter().map(|b| b.size))
+}
+
+pub fn worst_fit(size: Size, blocks: &[Block]) -> AlgoResult {
+    worst_fit_in(size, blocks.iter().map(|b| b.size))
+}
+
+// The `*_in` variants only look at the sizes of the free blocks, so callers that
+// cannot hand out a slice of `Block`s (like `allocator::FitAllocator`) can use them too.
+
+pub fn first_fit_in<I: IntoIterator<Item = Size>>(size: Size, sizes: I) -> AlgoResult {
+    for (i, block_size) in sizes.into_iter().enumerate() {
+        if block_size >= size {
             return AlgoResult::Ok(i);
         }
     }
     AlgoResult::None
 }
 
-pub fn best_fit(size: Size, blocks: &Vec<Block>) -> AlgoResult {
-    let mut best_block: AlgoResult = AlgoResult::None;
-    for (i, block) in blocks.iter().enumerate() {
-        if block.size >= size {
+pub fn best_fit_in<I: IntoIterator<Item = Size>>(size: Size, sizes: I) -> AlgoResult {
+    let mut best_block: Option<(usize, Size)> = None;
+    for (i, block_size) in sizes.into_iter().enumerate() {
+        if block_size >= size {
             match best_block {
-                AlgoResult::Ok(best) => {
-                    if block.size < blocks[best.clone()].size {
-                        best_block = AlgoResult::Ok(i);
+                Some((_, best)) => {
+                    if block_size < best {
+                        best_block = Some((i, block_size));
                     }
                 }
-                AlgoResult::None => {
-                    best_block = AlgoResult::Ok(i);
+                None => {
+                    best_block = Some((i, block_size));
                 }
             }
         }
     }
-    best_block
+    match best_block {
+        Some((i, _)) => AlgoResult::Ok(i),
+        None => AlgoResult::None,
+    }
 }
 
-pub fn worst_fit(size: Size, blocks: &Vec<Block>) -> AlgoResult {
-    let mut worst_block: AlgoResult = AlgoResult::None;
-    for (i, block) in blocks.iter().enumerate() {
-        if block.size >= size {
+pub fn worst_fit_in<I: IntoIterator<Item = Size>>(size: Size, sizes: I) -> AlgoResult {
+    let mut worst_block: Option<(usize, Size)> = None;
+    for (i, block_size) in sizes.into_iter().enumerate() {
+        if block_size >= size {
             match worst_block {
-                AlgoResult::Ok(worst) => {
-                    if block.size > blocks[worst.clone()].size {
-                        worst_block = AlgoResult::Ok(i);
+                Some((_, worst)) => {
+                    if block_size > worst {
+                        worst_block = Some((i, block_size));
                     }
                 }
-                AlgoResult::None => {
-                    worst_block = AlgoResult::Ok(i);
+                None => {
+                    worst_block = Some((i, block_size));
                 }
             }
         }
     }
-    worst_block
+    match worst_block {
+        Some((i, _)) => AlgoResult::Ok(i),
+        None => AlgoResult::None,
+    }
 }
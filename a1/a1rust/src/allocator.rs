@@ -0,0 +1,274 @@
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    cell::UnsafeCell,
+    ptr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{
+    algos::{best_fit_in, first_fit_in, worst_fit_in, AlgoResult},
+    block::{Address, Block, Id, Size},
+    layout::{check_layout, LayoutError},
+};
+
+// Every block starts with a header and ends with a footer, both holding the block size
+// with the lowest bit set while the block is in use. Block sizes are multiples of `UNIT`,
+// which keeps that bit free and every payload `UNIT` aligned.
+const UNIT: usize = 16;
+const HEADER: usize = UNIT;
+const FOOTER: usize = std::mem::size_of::<usize>();
+const MIN_BLOCK: usize = 2 * UNIT;
+const USED: usize = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    FirstFit,
+    BestFit,
+    WorstFit,
+}
+
+impl Policy {
+    fn select<I: IntoIterator<Item = Size>>(&self, size: Size, sizes: I) -> AlgoResult {
+        match self {
+            Policy::FirstFit => first_fit_in(size, sizes),
+            Policy::BestFit => best_fit_in(size, sizes),
+            Policy::WorstFit => worst_fit_in(size, sizes),
+        }
+    }
+}
+
+impl std::fmt::Display for Policy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Policy::FirstFit => write!(f, "FirstFit"),
+            Policy::BestFit => write!(f, "BestFit"),
+            Policy::WorstFit => write!(f, "WorstFit"),
+        }
+    }
+}
+
+struct Heap {
+    start: *mut u8,
+    size: usize,
+    initialized: bool,
+}
+
+pub struct FitAllocator {
+    region: *mut u8,
+    len: usize,
+    policy: Policy,
+    locked: AtomicBool,
+    heap: UnsafeCell<Heap>,
+}
+
+unsafe impl Sync for FitAllocator {}
+
+impl FitAllocator {
+    /// # Safety
+    ///
+    /// `region` must point to `len` bytes that stay valid, and are not used by anything
+    /// else, for as long as the allocator is.
+    pub const unsafe fn new(region: *mut u8, len: usize, policy: Policy) -> FitAllocator {
+        FitAllocator {
+            region,
+            len,
+            policy,
+            locked: AtomicBool::new(false),
+            heap: UnsafeCell::new(Heap {
+                start: ptr::null_mut(),
+                size: 0,
+                initialized: false,
+            }),
+        }
+    }
+
+    pub fn policy(&self) -> Policy {
+        self.policy
+    }
+
+    // Runs `f` with exclusive access to the heap. `f` must not allocate.
+    fn with_heap<T>(&self, f: impl FnOnce(&mut Heap) -> T) -> T {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            std::hint::spin_loop();
+        }
+        let heap = unsafe { &mut *self.heap.get() };
+        if !heap.initialized {
+            unsafe { heap.init(self.region, self.len) };
+        }
+        let res = f(heap);
+        self.locked.store(false, Ordering::Release);
+        res
+    }
+
+    pub fn size(&self) -> Size {
+        self.with_heap(|heap| Size(heap.size))
+    }
+
+    pub fn free_memory(&self) -> Size {
+        self.with_heap(|heap| {
+            Size(
+                heap.blocks()
+                    .filter(|b| b.is_free())
+                    .fold(0, |acc, b| acc + b.size.0),
+            )
+        })
+    }
+
+    // Applies the simulator's layout rules to the heap, plus the one extra guarantee
+    // boundary tags give: no two free blocks are ever next to each other.
+    pub fn check_layout(&self) -> Result<(), LayoutError> {
+        self.with_heap(|heap| {
+            check_layout(heap.blocks(), Size(heap.size))?;
+            let mut prev_free: Option<Block> = None;
+            for block in heap.blocks() {
+                if let Some(prev) = &prev_free {
+                    if block.is_free() {
+                        return Err(LayoutError::Uncoalesced(
+                            prev.end_addr.0,
+                            block.start_addr.0,
+                        ));
+                    }
+                }
+                prev_free = if block.is_free() { Some(block) } else { None };
+            }
+            Ok(())
+        })
+    }
+}
+
+unsafe impl GlobalAlloc for FitAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let padding = if layout.align() > UNIT {
+            layout.align()
+        } else {
+            0
+        };
+        let payload = layout.size().max(FOOTER) + padding;
+        let size = (HEADER + payload + FOOTER).next_multiple_of(UNIT);
+        let policy = self.policy;
+
+        self.with_heap(|heap| {
+            let free = heap.blocks().filter(|b| b.is_free()).map(|b| b.size);
+            let index = match policy.select(Size(size), free) {
+                AlgoResult::Ok(i) => i,
+                AlgoResult::None => return ptr::null_mut(),
+            };
+            let block = heap.blocks().filter(|b| b.is_free()).nth(index).unwrap();
+            let header = heap.start.add(block.start_addr.0);
+            heap.take(header, size);
+
+            let payload = header.add(HEADER);
+            if padding == 0 {
+                return payload;
+            }
+            // Over-aligned payloads are shifted inside the block. The distance back to the
+            // header is stored right before the payload so `dealloc` can find it again.
+            let mut offset = payload.align_offset(layout.align());
+            if offset < FOOTER {
+                offset += layout.align();
+            }
+            let aligned = payload.add(offset);
+            (aligned.sub(FOOTER) as *mut usize).write(offset + HEADER);
+            aligned
+        })
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let header = if layout.align() > UNIT {
+            ptr.sub((ptr.sub(FOOTER) as *const usize).read())
+        } else {
+            ptr.sub(HEADER)
+        };
+        self.with_heap(|heap| heap.free(header))
+    }
+}
+
+impl Heap {
+    unsafe fn init(&mut self, region: *mut u8, len: usize) {
+        let offset = region.align_offset(UNIT);
+        self.start = region.add(offset);
+        self.size = len.saturating_sub(offset) / UNIT * UNIT;
+        self.initialized = true;
+        if self.size >= MIN_BLOCK {
+            self.set(self.start, self.size, false);
+        } else {
+            self.size = 0;
+        }
+    }
+
+    unsafe fn tag(&self, header: *mut u8) -> usize {
+        (header as *const usize).read()
+    }
+
+    unsafe fn set(&mut self, header: *mut u8, size: usize, used: bool) {
+        let tag = if used { size | USED } else { size };
+        (header as *mut usize).write(tag);
+        (header.add(size - FOOTER) as *mut usize).write(tag);
+    }
+
+    fn end(&self) -> *mut u8 {
+        self.start.wrapping_add(self.size)
+    }
+
+    unsafe fn take(&mut self, header: *mut u8, size: usize) {
+        let block_size = self.tag(header) & !USED;
+        if block_size - size >= MIN_BLOCK {
+            self.set(header, size, true);
+            self.set(header.add(size), block_size - size, false);
+        } else {
+            self.set(header, block_size, true);
+        }
+    }
+
+    unsafe fn free(&mut self, header: *mut u8) {
+        let mut header = header;
+        let mut size = self.tag(header) & !USED;
+
+        let next = header.add(size);
+        if next < self.end() && self.tag(next) & USED == 0 {
+            size += self.tag(next);
+        }
+        if header > self.start {
+            let prev_tag = (header.sub(FOOTER) as *const usize).read();
+            if prev_tag & USED == 0 {
+                header = header.sub(prev_tag);
+                size += prev_tag;
+            }
+        }
+        self.set(header, size, false);
+    }
+
+    fn blocks(&self) -> HeapBlocks<'_> {
+        HeapBlocks {
+            heap: self,
+            offset: 0,
+        }
+    }
+}
+
+struct HeapBlocks<'a> {
+    heap: &'a Heap,
+    offset: usize,
+}
+
+impl Iterator for HeapBlocks<'_> {
+    type Item = Block;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.heap.size {
+            return None;
+        }
+        let tag = unsafe { self.heap.tag(self.heap.start.add(self.offset)) };
+        let size = Size(tag & !USED);
+        let start_addr = Address(self.offset);
+        self.offset += size.0.max(UNIT);
+        match tag & USED {
+            0 => Some(Block::new_free(size, start_addr)),
+            _ => Some(Block::new_used(Id(start_addr.0), size, start_addr)),
+        }
+    }
+}
@@ -3,41 +3,97 @@ use std::{
     ops::{Add, Sub},
 };
 
+pub trait CheckedAdd<Rhs> {
+    type Output;
+    fn checked_add(self, rhs: Rhs) -> Option<Self::Output>;
+}
+
+pub trait CheckedSub<Rhs> {
+    type Output;
+    fn checked_sub(self, rhs: Rhs) -> Option<Self::Output>;
+}
+
+// Splits a primitive into its magnitude and whether it is negative,
+// `None` if the magnitude does not fit into a usize.
+fn magnitude<T>(value: T) -> Option<(usize, bool)>
+where
+    T: Copy + TryInto<usize> + TryInto<i128>,
+{
+    if let Ok(value) = TryInto::<usize>::try_into(value) {
+        return Some((value, false));
+    }
+    match TryInto::<i128>::try_into(value) {
+        Ok(value) if value < 0 => Some((usize::try_from(value.unsigned_abs()).ok()?, true)),
+        _ => None,
+    }
+}
+
+// The operators panic on overflow instead of wrapping, use the checked variants
+// wherever the operands come from the input.
 macro_rules! impl_arith {
     ($for:ident, $other:ident) => {
-        impl Add<$other> for $for {
+        impl CheckedAdd<$other> for $for {
             type Output = $for;
-            fn add(self, rhs: $other) -> Self::Output {
-                $for(self.0 + rhs.0)
+            fn checked_add(self, rhs: $other) -> Option<Self::Output> {
+                self.0.checked_add(rhs.0).map($for)
             }
         }
 
-        impl Sub<$other> for $for {
+        impl CheckedSub<$other> for $for {
             type Output = $for;
-            fn sub(self, rhs: $other) -> Self::Output {
-                $for(self.0 - rhs.0)
+            fn checked_sub(self, rhs: $other) -> Option<Self::Output> {
+                self.0.checked_sub(rhs.0).map($for)
             }
         }
+
+        impl_arith!(@ops $for, $other);
     };
     ($for:ident, $other:ident, $($ty:ident),*) => {
         impl_arith!($for, $for);
         impl_arith!($for, $other);
         $(
-            impl Add<$ty> for $for {
+            impl CheckedAdd<$ty> for $for {
                 type Output = $for;
-                fn add(self, rhs: $ty) -> Self::Output {
-                    $for(self.0 + rhs as usize)
+                fn checked_add(self, rhs: $ty) -> Option<Self::Output> {
+                    match magnitude(rhs)? {
+                        (rhs, false) => self.0.checked_add(rhs),
+                        (rhs, true) => self.0.checked_sub(rhs),
+                    }
+                    .map($for)
                 }
             }
 
-            impl Sub<$ty> for $for {
+            impl CheckedSub<$ty> for $for {
                 type Output = $for;
-                fn sub(self, rhs: $ty) -> Self::Output {
-                    $for(self.0 - rhs as usize)
+                fn checked_sub(self, rhs: $ty) -> Option<Self::Output> {
+                    match magnitude(rhs)? {
+                        (rhs, false) => self.0.checked_sub(rhs),
+                        (rhs, true) => self.0.checked_add(rhs),
+                    }
+                    .map($for)
                 }
             }
+
+            impl_arith!(@ops $for, $ty);
         )*
     };
+    (@ops $for:ident, $rhs:ident) => {
+        impl Add<$rhs> for $for {
+            type Output = $for;
+            fn add(self, rhs: $rhs) -> Self::Output {
+                self.checked_add(rhs)
+                    .expect(concat!("attempt to add to ", stringify!($for), " with overflow"))
+            }
+        }
+
+        impl Sub<$rhs> for $for {
+            type Output = $for;
+            fn sub(self, rhs: $rhs) -> Self::Output {
+                self.checked_sub(rhs)
+                    .expect(concat!("attempt to subtract from ", stringify!($for), " with overflow"))
+            }
+        }
+    };
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
@@ -52,6 +108,7 @@ pub struct Address(pub usize);
 impl_arith!(Address, Size, usize, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
 impl_arith!(Size, Address, usize, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
 
+#[derive(Debug, Clone)]
 pub struct Block {
     pub id: Option<Id>,
     pub size: Size,
@@ -60,8 +117,16 @@ pub struct Block {
 }
 
 impl Block {
+    // The last address of a block, `None` if it is empty or runs past the end of the address space.
+    pub fn last_addr(start_addr: Address, size: Size) -> Option<Address> {
+        match size.0 {
+            0 => None,
+            _ => start_addr.checked_add(size - 1usize),
+        }
+    }
+
     pub fn new_free(size: Size, start_addr: Address) -> Self {
-        let end_addr = start_addr + size - 1;
+        let end_addr = Block::last_addr(start_addr, size).expect("block is empty or out of range");
         Block {
             id: None,
             size,
@@ -71,7 +136,7 @@ impl Block {
     }
 
     pub fn new_used(id: Id, size: Size, start_addr: Address) -> Self {
-        let end_addr = start_addr + size - 1;
+        let end_addr = Block::last_addr(start_addr, size).expect("block is empty or out of range");
         Block {
             id: Some(id),
             size,
@@ -97,6 +162,11 @@ impl Block {
         Block::new_free(size, s_addr)
     }
 
+    pub fn relocate(&mut self, start_addr: Address) {
+        self.start_addr = start_addr;
+        self.end_addr = start_addr + self.size - 1;
+    }
+
     pub fn as_free(&self) -> Block {
         Block::new_free(Size(self.size.0), Address(self.start_addr.0))
     }
@@ -111,6 +181,7 @@ impl std::fmt::Display for Block {
     }
 }
 
+#[derive(Default)]
 pub struct BlockVec(Vec<Block>);
 
 impl BlockVec {
@@ -134,15 +205,23 @@ impl BlockVec {
         &self.0[i]
     }
 
+    pub fn get_mut(&mut self, i: usize) -> &mut Block {
+        &mut self.0[i]
+    }
+
     pub fn len(&self) -> usize {
         self.0.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     pub fn as_vec(&self) -> &Vec<Block> {
         &self.0
     }
 
-    pub fn iter(&self) -> std::slice::Iter<Block> {
+    pub fn iter(&self) -> std::slice::Iter<'_, Block> {
         self.0.iter()
     }
 }
@@ -2,19 +2,45 @@ use std::str::{FromStr, Split};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Cmd {
-    Alloc(usize, usize),
+    Alloc(usize, usize, Option<usize>),
     Dealloc(usize),
     Compact,
     Output,
+    Slab(usize, usize),
+    Heap(usize, usize, usize),
+    Realloc(usize, usize, usize),
+    Write(usize, u8),
+    Verify(usize, u8),
+    Root(usize),
+    Unroot(usize),
+    Ref(usize, usize),
+    Collect(bool),
+    Retain(usize),
+    Release(usize),
+    Kill(usize),
+    Quota(usize, usize),
 }
 
 impl std::fmt::Display for Cmd {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
-            Cmd::Alloc(_, _) => write!(f, "A"),
+            Cmd::Alloc(_, _, _) => write!(f, "A"),
             Cmd::Dealloc(_) => write!(f, "D"),
             Cmd::Compact => write!(f, "C"),
             Cmd::Output => write!(f, "O"),
+            Cmd::Slab(_, _) => write!(f, "S"),
+            Cmd::Heap(_, _, _) => write!(f, "H"),
+            Cmd::Realloc(_, _, _) => write!(f, "R"),
+            Cmd::Write(_, _) => write!(f, "W"),
+            Cmd::Verify(_, _) => write!(f, "V"),
+            Cmd::Root(_) => write!(f, "T"),
+            Cmd::Unroot(_) => write!(f, "U"),
+            Cmd::Ref(_, _) => write!(f, "L"),
+            Cmd::Collect(_) => write!(f, "G"),
+            Cmd::Retain(_) => write!(f, "+"),
+            Cmd::Release(_) => write!(f, "-"),
+            Cmd::Kill(_) => write!(f, "K"),
+            Cmd::Quota(_, _) => write!(f, "Q"),
         }
     }
 }
@@ -27,15 +53,87 @@ impl FromStr for Cmd {
         let (cmd, iter) = next_string(iter);
         match cmd.as_str() {
             "A" => {
+                let (id, iter) = next_usize(iter);
                 let (size, iter) = next_usize(iter);
-                let (align, _) = next_usize(iter);
-                Ok(Cmd::Alloc(size, align))
+                let (pid, _) = next_opt_usize(iter);
+                Ok(Cmd::Alloc(id, size, pid))
             }
             "D" => {
                 let (id, _) = next_usize(iter);
                 Ok(Cmd::Dealloc(id))
             }
             "O" => Ok(Cmd::Output),
+            "S" => {
+                let (size, iter) = next_usize(iter);
+                let (page_size, _) = next_usize(iter);
+                // A page has to hold at least one object, or every page carved for it is lost.
+                if size == 0 || size > page_size {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!(
+                            "Slab objects of size {} do not fit pages of size {}",
+                            size, page_size
+                        ),
+                    ));
+                }
+                Ok(Cmd::Slab(size, page_size))
+            }
+            "H" => {
+                let (increment, iter) = next_usize(iter);
+                let (max, iter) = next_usize(iter);
+                let (trim_threshold, _) = next_usize(iter);
+                Ok(Cmd::Heap(increment, max, trim_threshold))
+            }
+            "R" => {
+                let (id, iter) = next_usize(iter);
+                let (new_id, iter) = next_usize(iter);
+                let (size, _) = next_usize(iter);
+                Ok(Cmd::Realloc(id, new_id, size))
+            }
+            "W" => {
+                let (id, iter) = next_usize(iter);
+                let (pattern, _) = next_pattern(iter)?;
+                Ok(Cmd::Write(id, pattern))
+            }
+            "V" => {
+                let (id, iter) = next_usize(iter);
+                let (pattern, _) = next_pattern(iter)?;
+                Ok(Cmd::Verify(id, pattern))
+            }
+            "T" => {
+                let (id, _) = next_usize(iter);
+                Ok(Cmd::Root(id))
+            }
+            "U" => {
+                let (id, _) = next_usize(iter);
+                Ok(Cmd::Unroot(id))
+            }
+            "L" => {
+                let (from, iter) = next_usize(iter);
+                let (to, _) = next_usize(iter);
+                Ok(Cmd::Ref(from, to))
+            }
+            "G" => {
+                let (compact, _) = next_opt_usize(iter);
+                Ok(Cmd::Collect(compact == Some(1)))
+            }
+            "+" => {
+                let (id, _) = next_usize(iter);
+                Ok(Cmd::Retain(id))
+            }
+            "-" => {
+                let (id, _) = next_usize(iter);
+                Ok(Cmd::Release(id))
+            }
+            "K" => {
+                let (pid, _) = next_usize(iter);
+                Ok(Cmd::Kill(pid))
+            }
+            "Q" => {
+                let (pid, iter) = next_usize(iter);
+                let (quota, _) = next_usize(iter);
+                Ok(Cmd::Quota(pid, quota))
+            }
             _ => Ok(Cmd::Compact),
         }
     }
@@ -49,24 +147,79 @@ fn next_usize(mut iter: Split<char>) -> (usize, Split<char>) {
     (iter.next().unwrap().parse().unwrap(), iter)
 }
 
+// Patterns are single bytes, anything larger would silently wrap around.
+fn next_pattern(iter: Split<char>) -> Result<(u8, Split<char>), std::io::Error> {
+    let (pattern, iter) = next_usize(iter);
+    match u8::try_from(pattern) {
+        Ok(pattern) => Ok((pattern, iter)),
+        Err(_) => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Pattern {} does not fit into a byte", pattern),
+        )),
+    }
+}
+
+fn next_opt_usize(mut iter: Split<char>) -> (Option<usize>, Split<char>) {
+    (iter.next().map(|s| s.parse().unwrap()), iter)
+}
+
 #[derive(Debug)]
 pub struct CmdVec {
     pub size: usize,
+    pub regions: Vec<(usize, usize)>,
+    pub order: Vec<usize>,
     pub cmds: Vec<Cmd>,
 }
 
 impl CmdVec {
     pub fn new(size: usize) -> CmdVec {
-        CmdVec { size, cmds: vec![] }
+        CmdVec {
+            size,
+            regions: vec![(0, size)],
+            order: vec![],
+            cmds: vec![],
+        }
     }
 
     pub fn add(&mut self, cmd: Cmd) {
         self.cmds.push(cmd);
     }
 
-    pub fn iter(&self) -> std::slice::Iter<Cmd> {
+    // Regions have to be declared in address order, each one above the previous.
+    pub fn add_region(&mut self, base: usize, size: usize) -> Result<(), std::io::Error> {
+        let (last_base, last_size) = self.regions.last().unwrap();
+        if size == 0 || base < last_base + last_size {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "Region {};{} overlaps or precedes the previous region",
+                    base, size
+                ),
+            ));
+        }
+        self.regions.push((base, size));
+        Ok(())
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Cmd> {
         self.cmds.iter()
     }
+
+    // Takes one line after the size. Region and order declarations are kept here,
+    // everything else is returned as a command.
+    pub fn parse_line(&mut self, line: &str) -> Result<Option<Cmd>, std::io::Error> {
+        if let Some(region) = line.strip_prefix("M;") {
+            let (base, iter) = next_usize(region.split(';'));
+            let (size, _) = next_usize(iter);
+            self.add_region(base, size)?;
+            return Ok(None);
+        }
+        if let Some(order) = line.strip_prefix("N;") {
+            self.order = order.split(';').map(|r| r.parse().unwrap()).collect();
+            return Ok(None);
+        }
+        line.parse::<Cmd>().map(Some)
+    }
 }
 
 impl FromStr for CmdVec {
@@ -77,8 +230,9 @@ impl FromStr for CmdVec {
         let size = lines.first().unwrap().parse().unwrap();
         let mut cmds = CmdVec::new(size);
         for line in lines[1..].iter() {
-            let cmd = line.parse::<Cmd>()?;
-            cmds.add(cmd);
+            if let Some(cmd) = cmds.parse_line(line)? {
+                cmds.add(cmd);
+            }
         }
         Ok(cmds)
     }
@@ -0,0 +1,78 @@
+use crate::block::Id;
+
+pub struct Collection {
+    pub instr_nr: usize,
+    pub blocks: usize,
+    pub bytes: usize,
+}
+
+impl std::fmt::Display for Collection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "G;{};{};{}", self.instr_nr, self.blocks, self.bytes)
+    }
+}
+
+#[derive(Default)]
+pub struct Gc {
+    pub roots: Vec<Id>,
+    pub refs: Vec<(Id, Id)>,
+    pub collections: Vec<Collection>,
+}
+
+impl Gc {
+    pub fn new() -> Gc {
+        Gc::default()
+    }
+
+    pub fn add_root(&mut self, id: Id) {
+        if !self.roots.contains(&id) {
+            self.roots.push(id);
+        }
+    }
+
+    pub fn remove_root(&mut self, id: Id) {
+        self.roots.retain(|r| *r != id);
+    }
+
+    pub fn add_ref(&mut self, from: Id, to: Id) {
+        if !self.refs.contains(&(from, to)) {
+            self.refs.push((from, to));
+        }
+    }
+
+    // Called whenever a block is freed, so a later block reusing the id starts out clean.
+    pub fn forget(&mut self, id: Id) {
+        self.remove_root(id);
+        self.refs.retain(|(from, to)| *from != id && *to != id);
+    }
+
+    pub fn rename(&mut self, id: Id, new_id: Id) {
+        for root in self.roots.iter_mut().filter(|r| **r == id) {
+            *root = new_id;
+        }
+        for (from, to) in self.refs.iter_mut() {
+            if *from == id {
+                *from = new_id;
+            }
+            if *to == id {
+                *to = new_id;
+            }
+        }
+    }
+
+    // Returns every id reachable from the roots by following references.
+    pub fn mark(&self) -> Vec<Id> {
+        let mut marked: Vec<Id> = vec![];
+        let mut stack = self.roots.clone();
+        while let Some(id) = stack.pop() {
+            if marked.contains(&id) {
+                continue;
+            }
+            marked.push(id);
+            for (_, to) in self.refs.iter().filter(|(from, _)| *from == id) {
+                stack.push(*to);
+            }
+        }
+        marked
+    }
+}
@@ -0,0 +1,43 @@
+use crate::block::Size;
+
+pub struct Growth {
+    pub increment: Size,
+    pub max: Size,
+    pub trim_threshold: Size,
+}
+
+impl Growth {
+    pub fn new(increment: Size, max: Size, trim_threshold: Size) -> Growth {
+        Growth {
+            increment,
+            max,
+            trim_threshold,
+        }
+    }
+
+    // Rounds `needed` up to a whole number of increments, the last one cut short at `max`.
+    // `None` if a memory of `size` bytes cannot grow by `needed` without passing `max`.
+    pub fn step(&self, size: Size, needed: Size) -> Option<Size> {
+        let room = self.max.0.checked_sub(size.0)?;
+        if needed.0 > room {
+            return None;
+        }
+        let increment = self.increment.0.max(1);
+        let step = needed.0.div_ceil(increment).saturating_mul(increment);
+        Some(Size(step.min(room)))
+    }
+}
+
+pub enum HeapEvent {
+    Grow(usize, usize, usize),
+    Trim(usize, usize, usize),
+}
+
+impl std::fmt::Display for HeapEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeapEvent::Grow(instr_nr, from, to) => write!(f, "G;{};{};{}", instr_nr, from, to),
+            HeapEvent::Trim(instr_nr, from, to) => write!(f, "T;{};{};{}", instr_nr, from, to),
+        }
+    }
+}
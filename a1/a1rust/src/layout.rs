@@ -0,0 +1,57 @@
+use std::borrow::Borrow;
+
+use thiserror::Error;
+
+use crate::block::{Address, Block, Size};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum LayoutError {
+    #[error("Block {0};{1} has an end address that does not match its size {2}.")]
+    Size(usize, usize, usize),
+
+    #[error("Block starting at {0} overlaps the previous block ending at {1}.")]
+    Overlap(usize, usize),
+
+    #[error("Addresses {0} to {1} are not covered by any block.")]
+    Gap(usize, usize),
+
+    #[error("Block ending at {0} lies outside of the memory of size {1}.")]
+    OutOfBounds(usize, usize),
+
+    #[error("Free blocks ending at {0} and starting at {1} were not merged.")]
+    Uncoalesced(usize, usize),
+}
+
+// The blocks have to be given in address order. Nothing is allocated while checking,
+// which keeps this usable from inside an allocator.
+pub fn check_layout<B, I>(blocks: I, size: Size) -> Result<(), LayoutError>
+where
+    B: Borrow<Block>,
+    I: IntoIterator<Item = B>,
+{
+    let mut next_addr = Address(0);
+    for block in blocks {
+        let block = block.borrow();
+        if block.size.0 == 0 || block.end_addr.0 + 1 != block.start_addr.0 + block.size.0 {
+            return Err(LayoutError::Size(
+                block.start_addr.0,
+                block.end_addr.0,
+                block.size.0,
+            ));
+        }
+        if block.start_addr < next_addr {
+            return Err(LayoutError::Overlap(block.start_addr.0, next_addr.0 - 1));
+        }
+        if block.start_addr > next_addr {
+            return Err(LayoutError::Gap(next_addr.0, block.start_addr.0 - 1));
+        }
+        if block.end_addr.0 >= size.0 {
+            return Err(LayoutError::OutOfBounds(block.end_addr.0, size.0));
+        }
+        next_addr = block.end_addr + 1;
+    }
+    if next_addr.0 < size.0 {
+        return Err(LayoutError::Gap(next_addr.0, size.0 - 1));
+    }
+    Ok(())
+}
@@ -0,0 +1,16 @@
+pub mod algos;
+pub mod allocator;
+pub mod block;
+pub mod cmd;
+pub mod gc;
+pub mod heap;
+pub mod layout;
+pub mod lifecycle;
+pub mod memory;
+pub mod paging;
+pub mod process;
+pub mod region;
+pub mod simulator;
+pub mod slab;
+pub mod tlb;
+pub mod workload;
@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+
+use crate::block::Id;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Allocated(usize),
+    Failed(usize),
+    Freed(usize),
+    Relocated(usize, Id),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdState {
+    Unused,
+    Live,
+    Freed,
+    Failed,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    #[default]
+    Reject,
+    Flag,
+}
+
+impl std::str::FromStr for DuplicatePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(DuplicatePolicy::Reject),
+            "flag" => Ok(DuplicatePolicy::Flag),
+            _ => Err(format!(
+                "Unknown duplicate policy {}, use reject or flag",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeallocReason {
+    NeverAllocated,
+    AllocFailed(usize),
+    DoubleFree(usize),
+    Relocated(usize, Id),
+}
+
+impl DeallocReason {
+    // 0 and 1 keep the meaning the output format has always had.
+    pub fn code(&self) -> usize {
+        match self {
+            DeallocReason::NeverAllocated => 0,
+            DeallocReason::AllocFailed(_) => 1,
+            DeallocReason::DoubleFree(_) => 2,
+            DeallocReason::Relocated(_, _) => 3,
+        }
+    }
+
+    pub fn explain(&self, id: Id) -> String {
+        match self {
+            DeallocReason::NeverAllocated => format!("block {} was never allocated", id.0),
+            DeallocReason::AllocFailed(instr_nr) => format!(
+                "block {} was never allocated, its allocation at instruction {} failed",
+                id.0, instr_nr
+            ),
+            DeallocReason::DoubleFree(instr_nr) => format!(
+                "block {} was already freed at instruction {}",
+                id.0, instr_nr
+            ),
+            DeallocReason::Relocated(instr_nr, new_id) => format!(
+                "block {} was moved to block {} at instruction {}",
+                id.0, new_id.0, instr_nr
+            ),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Lifecycle {
+    pub history: BTreeMap<Id, Vec<Event>>,
+}
+
+impl Lifecycle {
+    pub fn new() -> Lifecycle {
+        Lifecycle::default()
+    }
+
+    pub fn record(&mut self, id: Id, event: Event) {
+        self.history.entry(id).or_default().push(event);
+    }
+
+    pub fn last(&self, id: Id) -> Option<Event> {
+        self.history.get(&id)?.last().copied()
+    }
+
+    // A relocated id is as good as freed, its block lives on under the new id.
+    pub fn state(&self, id: Id) -> IdState {
+        match self.last(id) {
+            None => IdState::Unused,
+            Some(Event::Allocated(_)) => IdState::Live,
+            Some(Event::Freed(_)) | Some(Event::Relocated(_, _)) => IdState::Freed,
+            Some(Event::Failed(_)) => IdState::Failed,
+        }
+    }
+
+    // Why freeing `id` failed, judged by the last thing that happened to it.
+    pub fn dealloc_reason(&self, id: Id) -> DeallocReason {
+        match self.last(id) {
+            Some(Event::Failed(instr_nr)) => DeallocReason::AllocFailed(instr_nr),
+            Some(Event::Freed(instr_nr)) => DeallocReason::DoubleFree(instr_nr),
+            Some(Event::Relocated(instr_nr, new_id)) => DeallocReason::Relocated(instr_nr, new_id),
+            Some(Event::Allocated(_)) | None => DeallocReason::NeverAllocated,
+        }
+    }
+}
@@ -1,40 +1,35 @@
-use std::{io::Write, str::FromStr};
+use std::io::{Read, Write};
 
-pub mod algos;
-pub mod block;
-pub mod cmd;
-pub mod memory;
+use a1rust::simulator::{self, Options};
 
 fn main() {
-    let path = std::env::args()
-        .nth(1)
-        .expect("Please provide a path to the input file");
-    let in_path = format!("{}.in", path);
-    let data = std::fs::read_to_string(in_path).unwrap();
-    let cmds = cmd::CmdVec::from_str(&data).unwrap();
-    let fns: Vec<(&str, algos::Algo)> = vec![
-        ("FirstFit", algos::first_fit),
-        ("BestFit", algos::best_fit),
-        ("WorstFit", algos::worst_fit),
-    ];
-    let mut partitions: Vec<(&str, memory::Memory)> = vec![];
-    let mut memory = memory::Memory::new(cmds.size);
-    for (name, algo) in fns.iter() {
-        let mut mem = memory.with_out_count();
-        for cmd in cmds.iter() {
-            mem.exec(cmd, (*name, algo.clone()), &path);
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let path = args.iter().find(|a| !a.starts_with("--"));
+    let options = Options::from_args(&args);
+
+    // Without a path, or with `-`, the input comes from stdin and the output goes to stdout.
+    let path = match path.map(|p| p.as_str()) {
+        None | Some("-") => {
+            let stdin = std::io::stdin();
+            let mut stdout = std::io::stdout().lock();
+            if options.paging || options.timed {
+                let mut data = String::new();
+                stdin.lock().read_to_string(&mut data).unwrap();
+                let out = simulator::run(&data, &options, "stdin");
+                stdout.write_all(out.as_bytes()).unwrap();
+            } else if let Err(err) = simulator::stream(stdin.lock(), &mut stdout, &options) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+            return;
         }
-        memory = mem.with_out_count();
+        Some(path) => path,
+    };
 
-        partitions.push((name, mem));
-    }
+    let in_path = format!("{}.in", path);
+    let data = std::fs::read_to_string(in_path).unwrap();
+    let out = simulator::run(&data, &options, path);
 
     let mut file = std::fs::File::create(format!("{}.out", path)).unwrap();
-    let out = partitions
-        .iter()
-        .map(|(n, p)| p.output(n))
-        .collect::<Vec<String>>()
-        .join("");
-
     file.write_all(out.as_bytes()).unwrap();
 }
@@ -1,15 +1,39 @@
-use std::io::Write;
+use std::{
+    cmp::{max, min},
+    collections::BTreeMap,
+    io::Write,
+};
 
 use crate::{
     algos::{Algo, AlgoResult},
     block::{Address, Block, BlockVec, Id, Size},
     cmd::Cmd,
+    gc::{Collection, Gc},
+    heap::{Growth, HeapEvent},
+    layout::{check_layout, LayoutError},
+    lifecycle::{DeallocReason, DuplicatePolicy, Event, IdState, Lifecycle},
+    process::Processes,
+    region::Region,
+    slab::SlabCache,
+    workload::Stats,
 };
 
 pub enum Result {
     Ok,
     AllocErr(Id, usize, usize),
-    DeallocErr(Id, usize, usize),
+    DeallocErr(Id, usize, DeallocReason),
+    ReallocErr(Id, usize, usize),
+    WriteErr(Id, usize),
+    CorruptionErr(Id, usize),
+    RootErr(Id, usize),
+    RefErr(Id, usize),
+    RetainErr(Id, usize),
+    ReleaseErr(Id, usize),
+    LeakErr(Id, usize),
+    QuotaErr(Id, usize, usize),
+    KillErr(usize, usize),
+    DuplicateErr(Id, usize),
+    SizeErr(Id, usize),
 }
 
 impl std::fmt::Display for Result {
@@ -17,15 +41,49 @@ impl std::fmt::Display for Result {
         match self {
             Result::Ok => write!(f, "OK"),
             Result::AllocErr(_, instr_nr, size) => write!(f, "A;{};{}", instr_nr, size),
-            Result::DeallocErr(_, instr_nr, reason) => write!(f, "D;{};{}", instr_nr, reason),
+            Result::DeallocErr(_, instr_nr, reason) => {
+                write!(f, "D;{};{}", instr_nr, reason.code())
+            }
+            Result::ReallocErr(_, instr_nr, reason) => write!(f, "R;{};{}", instr_nr, reason),
+            Result::WriteErr(id, instr_nr) => write!(f, "W;{};{}", instr_nr, id.0),
+            Result::CorruptionErr(id, instr_nr) => write!(f, "V;{};{}", instr_nr, id.0),
+            Result::RootErr(id, instr_nr) => write!(f, "T;{};{}", instr_nr, id.0),
+            Result::RefErr(id, instr_nr) => write!(f, "L;{};{}", instr_nr, id.0),
+            Result::RetainErr(id, instr_nr) => write!(f, "+;{};{}", instr_nr, id.0),
+            Result::ReleaseErr(id, instr_nr) => write!(f, "-;{};{}", instr_nr, id.0),
+            Result::LeakErr(id, count) => write!(f, "Leak;{};{}", id.0, count),
+            Result::QuotaErr(_, instr_nr, pid) => write!(f, "Q;{};{}", instr_nr, pid),
+            Result::KillErr(pid, instr_nr) => write!(f, "K;{};{}", instr_nr, pid),
+            Result::DuplicateErr(id, instr_nr) => write!(f, "Dup;{};{}", instr_nr, id.0),
+            Result::SizeErr(id, instr_nr) => write!(f, "Size;{};{}", instr_nr, id.0),
         }
     }
 }
 
+enum Extent {
+    Used(usize),
+    Page(usize, usize),
+}
+
 pub struct Memory {
     pub size: usize,
+    pub data: Vec<u8>,
     pub free_blocks: BlockVec,
     pub used_blocks: BlockVec,
+    pub slabs: Vec<SlabCache>,
+    pub growth: Option<Growth>,
+    heap_events: Vec<HeapEvent>,
+    pub gc: Gc,
+    pub refcounts: BTreeMap<Id, usize>,
+    pub processes: Processes,
+    pub workload: Option<Stats>,
+    pub lifecycle: Lifecycle,
+    pub verbose: bool,
+    pub duplicates: DuplicatePolicy,
+    pub regions: Vec<Region>,
+    pub order: Vec<usize>,
+    // How many bytes at the start of every block hold the pattern of its last `W`.
+    written: BTreeMap<Id, usize>,
     errors: Vec<Result>,
     instr_cnt: usize,
     out_cnt: usize,
@@ -33,12 +91,37 @@ pub struct Memory {
 
 impl Memory {
     pub fn new(size: usize) -> Memory {
+        Memory::with_regions(&[(0, size)])
+    }
+
+    // Regions are given as `(base, size)` in address order and must not overlap.
+    pub fn with_regions(declared: &[(usize, usize)]) -> Memory {
         let mut free_blocks = BlockVec::new();
-        free_blocks.add(Block::new_free(Size(size), Address(0)));
+        let mut regions = vec![];
+        let mut size = 0;
+        for (base, region_size) in declared.iter() {
+            regions.push(Region::new(Address(*base), Size(*region_size), size));
+            free_blocks.add(Block::new_free(Size(*region_size), Address(*base)));
+            size += region_size;
+        }
         Memory {
             size,
+            data: vec![0; size],
             free_blocks,
             used_blocks: BlockVec::new(),
+            slabs: vec![],
+            growth: None,
+            heap_events: vec![],
+            gc: Gc::new(),
+            refcounts: BTreeMap::new(),
+            processes: Processes::new(),
+            workload: None,
+            lifecycle: Lifecycle::new(),
+            verbose: false,
+            duplicates: DuplicatePolicy::default(),
+            regions,
+            order: vec![],
+            written: BTreeMap::new(),
             errors: vec![],
             instr_cnt: 0,
             out_cnt: 0,
@@ -49,8 +132,9 @@ impl Memory {
         self.incr();
         let (name, algo) = algo;
         let res = match cmd {
-            Cmd::Alloc(id, size) => self.alloc(Id(id.clone()), Size(size.clone()), algo),
-            Cmd::Dealloc(id) => self.dealloc(Id(id.clone())),
+            Cmd::Alloc(id, size, None) => self.alloc(Id(*id), Size(*size), algo),
+            Cmd::Alloc(id, size, Some(pid)) => self.alloc_owned(Id(*id), Size(*size), *pid, algo),
+            Cmd::Dealloc(id) => self.dealloc(Id(*id)),
             Cmd::Compact => self.compact(),
             Cmd::Output => {
                 let mut out = "".to_owned();
@@ -70,6 +154,36 @@ impl Memory {
                 self.out_cnt += 1;
                 Result::Ok
             }
+            Cmd::Slab(size, page_size) => {
+                self.slabs
+                    .push(SlabCache::new(Size(*size), Size(*page_size)));
+                Result::Ok
+            }
+            Cmd::Heap(increment, max, trim_threshold) => {
+                self.growth = Some(Growth::new(
+                    Size(*increment),
+                    Size(*max),
+                    Size(*trim_threshold),
+                ));
+                Result::Ok
+            }
+            Cmd::Realloc(id, new_id, size) => self.realloc(Id(*id), Id(*new_id), Size(*size), algo),
+            Cmd::Write(id, pattern) => self.write(Id(*id), *pattern),
+            Cmd::Verify(id, pattern) => self.verify(Id(*id), *pattern),
+            Cmd::Root(id) => self.root(Id(*id)),
+            Cmd::Unroot(id) => {
+                self.gc.remove_root(Id(*id));
+                Result::Ok
+            }
+            Cmd::Ref(from, to) => self.add_ref(Id(*from), Id(*to)),
+            Cmd::Collect(compact) => self.collect(*compact),
+            Cmd::Retain(id) => self.retain(Id(*id)),
+            Cmd::Release(id) => self.release_ref(Id(*id)),
+            Cmd::Kill(pid) => self.kill(*pid),
+            Cmd::Quota(pid, quota) => {
+                self.processes.set_quota(*pid, *quota);
+                Result::Ok
+            }
         };
 
         match res {
@@ -78,88 +192,583 @@ impl Memory {
         }
     }
 
+    pub fn check_layout(&self) -> std::result::Result<(), LayoutError> {
+        let mut blocks: Vec<Block> = self
+            .used_blocks
+            .iter()
+            .chain(self.free_blocks.iter())
+            .cloned()
+            .collect();
+        // Only the extent of a slab page matters for the layout, not what it holds.
+        for cache in self.slabs.iter() {
+            for slab in cache.slabs.iter() {
+                blocks.push(Block::new_free(cache.page_size, slab.start_addr));
+            }
+        }
+        blocks.sort_by_key(|b| b.start_addr);
+        if let Some(b) = blocks
+            .iter()
+            .find(|b| !self.regions.iter().any(|r| r.contains(b.start_addr)))
+        {
+            return Err(LayoutError::OutOfBounds(b.end_addr.0, self.size));
+        }
+        // Every region is checked on its own, as if it started at address 0.
+        for region in self.regions.iter() {
+            let local = blocks
+                .iter()
+                .filter(|b| region.contains(b.start_addr))
+                .map(|b| {
+                    let mut local = b.clone();
+                    local.relocate(Address(b.start_addr.0 - region.base.0));
+                    local
+                });
+            check_layout(local, region.size)?;
+        }
+        Ok(())
+    }
+
+    // Reference counted blocks that are still live at the end of the trace have leaked.
+    pub fn finish(&mut self) {
+        for (id, count) in self.refcounts.iter() {
+            self.errors.push(Result::LeakErr(*id, *count));
+        }
+    }
+
+    pub fn errors(&self) -> &[Result] {
+        &self.errors
+    }
+
+    // What `O` writes to a file, for when there is no file to write to.
+    pub fn checkpoint(&mut self, algo_name: &str) -> String {
+        self.incr();
+        self.out_cnt += 1;
+        self.output(algo_name)
+    }
+
     pub fn get_free_memory(&self) -> usize {
         self.free_blocks.iter().fold(0, |acc, b| acc + b.size.0)
     }
 
     pub fn with_out_count(&self) -> Memory {
-        let mut free_blocks = BlockVec::new();
-        free_blocks.add(Block::new_free(Size(self.size.clone()), Address(0)));
-        Self {
-            size: self.size,
-            out_cnt: self.out_cnt,
-            free_blocks,
-            used_blocks: BlockVec::new(),
-            errors: vec![],
-            instr_cnt: 0,
-        }
+        let declared: Vec<(usize, usize)> = self
+            .regions
+            .iter()
+            .map(|r| (r.base.0, r.init_size.0))
+            .collect();
+        let mut memory = Memory::with_regions(&declared);
+        memory.order = self.order.clone();
+        memory.out_cnt = self.out_cnt;
+        memory.verbose = self.verbose;
+        memory.duplicates = self.duplicates;
+        memory
     }
 
-    fn alloc(&mut self, id: Id, size: Size, func: fn(Size, &Vec<Block>) -> AlgoResult) -> Result {
-        let index = func(size, &self.free_blocks.as_vec());
-        match index {
-            AlgoResult::Ok(i) => {
-                let mut block = self.free_blocks.pop(i);
-                let new_block = Block::new_used(id, size, block.start_addr);
-                if new_block.size < block.size {
-                    block.start_addr = block.start_addr + size;
-                    block.size = block.size - size;
-                    self.free_blocks.add(block);
+    fn alloc(&mut self, id: Id, size: Size, func: Algo) -> Result {
+        if self.lifecycle.state(id) == IdState::Live {
+            match self.duplicates {
+                DuplicatePolicy::Reject => return Result::DuplicateErr(id, self.instr_cnt),
+                // The new block takes the place of the live one, so an id never has two blocks
+                // of which `D` would only free one.
+                DuplicatePolicy::Flag => {
+                    self.errors.push(Result::DuplicateErr(id, self.instr_cnt));
+                    self.discard(id);
                 }
-                self.used_blocks.add(new_block);
+            }
+        }
+        let res = self.place(id, size, func);
+        let event = match res {
+            Result::Ok => Event::Allocated(self.instr_cnt),
+            _ => Event::Failed(self.instr_cnt),
+        };
+        self.lifecycle.record(id, event);
+        res
+    }
+
+    // An empty block, or one that could not be addressed even above the top of the memory,
+    // can never be placed no matter how much is free.
+    fn place(&mut self, id: Id, size: Size, func: Algo) -> Result {
+        if Block::last_addr(self.top(), size).is_none() {
+            return Result::SizeErr(id, self.instr_cnt);
+        }
+        if let Some(i) = self.slabs.iter().position(|c| c.obj_size == size) {
+            return self.slab_alloc(i, id, func);
+        }
+        match self.carve(size, func) {
+            Some(start_addr) => {
+                self.used_blocks.add(Block::new_used(id, size, start_addr));
                 Result::Ok
             }
-            AlgoResult::None => Result::AllocErr(id, self.instr_cnt, self.get_free_memory()),
+            None => Result::AllocErr(id, self.instr_cnt, self.get_free_memory()),
         }
     }
 
-    fn dealloc(&mut self, id: Id) -> Result {
-        for (i, b) in self.used_blocks.iter().enumerate() {
-            if b.id.unwrap() == id {
-                let block = self.used_blocks.pop(i).as_free();
-                for (i, b) in self.free_blocks.iter().enumerate() {
-                    if block.can_merge(b) {
-                        let new_block = block.merge(b);
-                        self.free_blocks.pop(i);
-                        self.free_blocks.add(new_block);
-                        return Result::Ok;
-                    }
+    fn slab_alloc(&mut self, cache: usize, id: Id, func: Algo) -> Result {
+        if self.slabs[cache].alloc(id) {
+            return Result::Ok;
+        }
+        let page_size = self.slabs[cache].page_size;
+        if self.slabs[cache].per_page() == 0 {
+            return Result::AllocErr(id, self.instr_cnt, self.get_free_memory());
+        }
+        match self.carve(page_size, func) {
+            Some(start_addr) => {
+                self.slabs[cache].add_page(start_addr);
+                match self.slabs[cache].alloc(id) {
+                    true => Result::Ok,
+                    false => Result::AllocErr(id, self.instr_cnt, self.get_free_memory()),
                 }
-                self.free_blocks.add(block);
+            }
+            None => Result::AllocErr(id, self.instr_cnt, self.get_free_memory()),
+        }
+    }
+
+    fn carve(&mut self, size: Size, func: Algo) -> Option<Address> {
+        let i = match self.select(size, func) {
+            Some(i) => i,
+            None if self.grow(size) => self.select(size, func)?,
+            None => return None,
+        };
+        let mut block = self.free_blocks.pop(i);
+        let start_addr = block.start_addr;
+        if size < block.size {
+            block.start_addr = block.start_addr + size;
+            block.size = block.size - size;
+            self.free_blocks.add(block);
+        }
+        Some(start_addr)
+    }
+
+    // Runs the algorithm on the free blocks of one region after the other, in spill order,
+    // and returns the index of the chosen block in the whole free list.
+    fn select(&self, size: Size, func: Algo) -> Option<usize> {
+        for r in self.spill_order() {
+            let region = self.regions[r];
+            let (indices, blocks): (Vec<usize>, Vec<Block>) = self
+                .free_blocks
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| region.contains(b.start_addr))
+                .map(|(i, b)| (i, b.clone()))
+                .unzip();
+            if let AlgoResult::Ok(i) = func(size, &blocks) {
+                return Some(indices[i]);
+            }
+        }
+        None
+    }
+
+    // The configured order first, followed by every region it leaves out.
+    pub fn spill_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = self
+            .order
+            .iter()
+            .copied()
+            .filter(|r| *r < self.regions.len())
+            .collect();
+        for r in 0..self.regions.len() {
+            if !order.contains(&r) {
+                order.push(r);
+            }
+        }
+        order
+    }
+
+    fn region_of(&self, addr: Address) -> &Region {
+        self.regions.iter().find(|r| r.contains(addr)).unwrap()
+    }
+
+    fn offset(&self, addr: Address) -> usize {
+        self.region_of(addr).offset_of(addr)
+    }
+
+    fn top(&self) -> Address {
+        self.regions.last().unwrap().end()
+    }
+
+    // Blocks in neighbouring regions are never merged, even if the regions touch.
+    fn release(&mut self, block: Block) {
+        let mut block = block;
+        let region = *self.region_of(block.start_addr);
+        while let Some(i) = self
+            .free_blocks
+            .iter()
+            .position(|b| block.can_merge(b) && region.contains(b.start_addr))
+        {
+            block = block.merge(self.free_blocks.get(i));
+            self.free_blocks.pop(i);
+        }
+        self.free_blocks.add(block);
+        self.trim();
+    }
+
+    fn top_free_block(&self) -> Option<usize> {
+        self.free_blocks
+            .iter()
+            .position(|b| b.end_addr + 1 == self.top())
+    }
+
+    // Extends the top free block, or appends a new one, so that `size` fits at the top.
+    // Only the last region grows, nothing lies above it.
+    fn grow(&mut self, size: Size) -> bool {
+        let growth = match &self.growth {
+            Some(growth) => growth,
+            None => return false,
+        };
+        let top = self.top_free_block();
+        let available = match top {
+            Some(i) => self.free_blocks.get(i).size,
+            None => Size(0),
+        };
+        let step = match growth.step(Size(self.size), size - available) {
+            Some(step) => step,
+            None => return false,
+        };
+
+        let from = self.size;
+        let top_addr = self.top();
+        self.size += step.0;
+        self.data.resize(self.size, 0);
+        let region = self.regions.last_mut().unwrap();
+        region.size = region.size + step;
+        match top {
+            Some(i) => {
+                let block = self.free_blocks.get_mut(i);
+                block.size = block.size + step;
+                block.end_addr = block.end_addr + step;
+            }
+            None => self.free_blocks.add(Block::new_free(step, top_addr)),
+        }
+        self.heap_events
+            .push(HeapEvent::Grow(self.instr_cnt, from, self.size));
+        true
+    }
+
+    // Gives a trailing free region above the threshold back, never going below the initial size.
+    fn trim(&mut self) {
+        let growth = match &self.growth {
+            Some(growth) => growth,
+            None => return,
+        };
+        let top = match self.top_free_block() {
+            Some(i) => i,
+            None => return,
+        };
+        let region = *self.regions.last().unwrap();
+        let block = self.free_blocks.get(top);
+        let new_end = max(region.base + region.init_size, block.start_addr);
+        if block.size <= growth.trim_threshold || new_end >= region.end() {
+            return;
+        }
+
+        if block.start_addr == new_end {
+            self.free_blocks.pop(top);
+        } else {
+            let block = self.free_blocks.get_mut(top);
+            block.size = Size(new_end.0 - block.start_addr.0);
+            block.end_addr = new_end - 1;
+        }
+        let new_size = self.size - (region.end().0 - new_end.0);
+        self.heap_events
+            .push(HeapEvent::Trim(self.instr_cnt, self.size, new_size));
+        self.size = new_size;
+        self.data.truncate(new_size);
+        self.regions.last_mut().unwrap().size = Size(new_end.0 - region.base.0);
+    }
+
+    // `D` hands back one reference like `-`, a retained block stays until its count drops to zero.
+    fn dealloc(&mut self, id: Id) -> Result {
+        if let Some(count) = self.refcounts.get_mut(&id) {
+            if *count > 1 {
+                *count -= 1;
                 return Result::Ok;
             }
         }
-        Result::DeallocErr(id, self.instr_cnt, self.did_try_allocating(id))
+        self.destroy(id)
     }
 
-    fn did_try_allocating(&self, id: Id) -> usize {
-        let did_try: Option<&Result> = self.errors.iter().find(|e| match e {
-            Result::AllocErr(i, _, _) => i == &id,
-            _ => false,
+    fn destroy(&mut self, id: Id) -> Result {
+        if !self.discard(id) {
+            return Result::DeallocErr(id, self.instr_cnt, self.lifecycle.dealloc_reason(id));
+        }
+        self.lifecycle.record(id, Event::Freed(self.instr_cnt));
+        Result::Ok
+    }
+
+    // Frees the block and forgets everything recorded about it, leaving its lifecycle alone.
+    fn discard(&mut self, id: Id) -> bool {
+        if !self.free(id) {
+            return false;
+        }
+        self.gc.forget(id);
+        self.refcounts.remove(&id);
+        self.processes.forget(id);
+        self.written.remove(&id);
+        true
+    }
+
+    fn alloc_owned(&mut self, id: Id, size: Size, pid: usize, func: Algo) -> Result {
+        if let Some(quota) = self.processes.quota(pid) {
+            if self.process_memory(pid) + size.0 > quota {
+                // Like any failed allocation, unless the id still names a live block.
+                if self.lifecycle.state(id) != IdState::Live {
+                    self.lifecycle.record(id, Event::Failed(self.instr_cnt));
+                }
+                return Result::QuotaErr(id, self.instr_cnt, pid);
+            }
+        }
+        let res = self.alloc(id, size, func);
+        if let Result::Ok = res {
+            self.processes.own(id, pid);
+        }
+        res
+    }
+
+    pub fn process_memory(&self, pid: usize) -> usize {
+        self.processes
+            .blocks(pid)
+            .iter()
+            .filter_map(|id| self.locate(*id))
+            .fold(0, |acc, (_, size)| acc + size.0)
+    }
+
+    // Every hole a freed block touches is merged with it by `release`.
+    fn kill(&mut self, pid: usize) -> Result {
+        if !self.processes.quotas.contains_key(&pid) {
+            return Result::KillErr(pid, self.instr_cnt);
+        }
+        for id in self.processes.blocks(pid) {
+            self.destroy(id);
+        }
+        Result::Ok
+    }
+
+    // A block only becomes reference counted once it is retained or released,
+    // starting out with the reference its allocation handed out.
+    fn retain(&mut self, id: Id) -> Result {
+        if self.locate(id).is_none() {
+            return Result::RetainErr(id, self.instr_cnt);
+        }
+        *self.refcounts.entry(id).or_insert(1) += 1;
+        Result::Ok
+    }
+
+    fn release_ref(&mut self, id: Id) -> Result {
+        if self.locate(id).is_none() {
+            return Result::ReleaseErr(id, self.instr_cnt);
+        }
+        let count = self.refcounts.entry(id).or_insert(1);
+        *count -= 1;
+        if *count == 0 {
+            return self.dealloc(id);
+        }
+        Result::Ok
+    }
+
+    fn free(&mut self, id: Id) -> bool {
+        if let Some(i) = self.slabs.iter().position(|c| c.contains(id)) {
+            if let Some(start_addr) = self.slabs[i].dealloc(id) {
+                let page_size = self.slabs[i].page_size;
+                self.release(Block::new_free(page_size, start_addr));
+            }
+            return true;
+        }
+        match self.used_blocks.iter().position(|b| b.id == Some(id)) {
+            Some(i) => {
+                let block = self.used_blocks.pop(i).as_free();
+                self.release(block);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn live_blocks(&self) -> Vec<(Id, Size)> {
+        let mut blocks: Vec<(Id, Size)> = self
+            .used_blocks
+            .iter()
+            .map(|b| (b.id.unwrap(), b.size))
+            .collect();
+        for cache in self.slabs.iter() {
+            blocks.extend(cache.ids().map(|id| (id, cache.obj_size)));
+        }
+        blocks
+    }
+
+    fn root(&mut self, id: Id) -> Result {
+        if self.locate(id).is_none() {
+            return Result::RootErr(id, self.instr_cnt);
+        }
+        self.gc.add_root(id);
+        Result::Ok
+    }
+
+    fn add_ref(&mut self, from: Id, to: Id) -> Result {
+        for id in [from, to] {
+            if self.locate(id).is_none() {
+                return Result::RefErr(id, self.instr_cnt);
+            }
+        }
+        self.gc.add_ref(from, to);
+        Result::Ok
+    }
+
+    fn collect(&mut self, compact: bool) -> Result {
+        let marked = self.gc.mark();
+        let garbage: Vec<(Id, Size)> = self
+            .live_blocks()
+            .into_iter()
+            .filter(|(id, _)| !marked.contains(id))
+            .collect();
+        for (id, _) in garbage.iter() {
+            self.dealloc(*id);
+        }
+        self.gc.collections.push(Collection {
+            instr_nr: self.instr_cnt,
+            blocks: garbage.len(),
+            bytes: garbage.iter().fold(0, |acc, (_, size)| acc + size.0),
         });
 
-        match did_try {
-            Some(Result::AllocErr(_, _, _)) => 1,
-            _ => 0,
+        match compact {
+            true => self.compact(),
+            false => Result::Ok,
         }
     }
 
-    fn compact(&mut self) -> Result {
-        let mut nf_blocks = BlockVec::new();
-        let mut nu_blocks = BlockVec::new();
-        let f_mem = self.get_free_memory();
+    // The block keeps its data up to the smaller of both sizes. Moving a block onto its own
+    // id would need both blocks live under one id at once, so it is rejected.
+    fn realloc(&mut self, id: Id, new_id: Id, size: Size, func: Algo) -> Result {
+        let (start_addr, old_size) = match self.locate(id) {
+            Some(location) => location,
+            None => return Result::ReallocErr(id, self.instr_cnt, 0),
+        };
+        if id == new_id {
+            return Result::ReallocErr(id, self.instr_cnt, 2);
+        }
+        let owner = self.processes.owner(id);
+        if let Some(pid) = owner {
+            if let Some(quota) = self.processes.quota(pid) {
+                if self.process_memory(pid) - old_size.0 + size.0 > quota {
+                    return Result::QuotaErr(id, self.instr_cnt, pid);
+                }
+            }
+        }
+        match self.alloc(new_id, size, func) {
+            Result::Ok => (),
+            Result::AllocErr(_, _, _) => return Result::ReallocErr(id, self.instr_cnt, 1),
+            err => return err,
+        }
+        if let Some(pid) = owner {
+            self.processes.own(new_id, pid);
+        }
+
+        let (new_start_addr, _) = self.locate(new_id).unwrap();
+        let len = min(old_size.0, size.0);
+        let (from, to) = (self.offset(start_addr), self.offset(new_start_addr));
+        self.data.copy_within(from..from + len, to);
+        if let Some(written) = self.written.get(&id) {
+            self.written.insert(new_id, min(*written, size.0));
+        }
+        self.gc.rename(id, new_id);
+        if let Some(count) = self.refcounts.remove(&id) {
+            self.refcounts.insert(new_id, count);
+        }
+        let res = self.dealloc(id);
+        self.lifecycle
+            .record(id, Event::Relocated(self.instr_cnt, new_id));
+        res
+    }
 
-        let mut last_start_addr = Address(0);
-        for block in self.used_blocks.iter() {
-            let new_block =
-                Block::new_used(block.id.unwrap(), block.size, last_start_addr + block.size);
-            last_start_addr = new_block.end_addr + 1;
-            nu_blocks.add(new_block);
+    pub fn locate(&self, id: Id) -> Option<(Address, Size)> {
+        if let Some(b) = self.used_blocks.iter().find(|b| b.id == Some(id)) {
+            return Some((b.start_addr, b.size));
         }
-        nf_blocks.add(Block::new_free(Size(f_mem), Address(self.size - f_mem)));
+        self.slabs
+            .iter()
+            .find_map(|c| Some((c.locate(id)?, c.obj_size)))
+    }
 
-        self.free_blocks = nf_blocks;
-        self.used_blocks = nu_blocks;
+    fn write(&mut self, id: Id, pattern: u8) -> Result {
+        match self.locate(id) {
+            Some((start_addr, size)) => {
+                let offset = self.offset(start_addr);
+                let bytes = &mut self.data[offset..offset + size.0];
+                for (offset, byte) in bytes.iter_mut().enumerate() {
+                    *byte = pattern_byte(pattern, offset);
+                }
+                self.written.insert(id, size.0);
+                Result::Ok
+            }
+            None => Result::WriteErr(id, self.instr_cnt),
+        }
+    }
+
+    // Only what was written is checked, a block grown by `R` has no pattern in its tail.
+    fn verify(&self, id: Id, pattern: u8) -> Result {
+        match self.locate(id) {
+            Some((start_addr, size)) => {
+                let offset = self.offset(start_addr);
+                let len = self.written.get(&id).copied().unwrap_or(size.0);
+                let bytes = &self.data[offset..offset + len];
+                let intact = bytes
+                    .iter()
+                    .enumerate()
+                    .all(|(offset, byte)| *byte == pattern_byte(pattern, offset));
+                match intact {
+                    true => Result::Ok,
+                    false => Result::CorruptionErr(id, self.instr_cnt),
+                }
+            }
+            None => Result::CorruptionErr(id, self.instr_cnt),
+        }
+    }
+
+    fn compact(&mut self) -> Result {
+        // Slab pages move as a whole, so they are laid out together with the used blocks.
+        let mut extents: Vec<(Address, Extent)> = self
+            .used_blocks
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (b.start_addr, Extent::Used(i)))
+            .collect();
+        for (c, cache) in self.slabs.iter().enumerate() {
+            for (s, slab) in cache.slabs.iter().enumerate() {
+                extents.push((slab.start_addr, Extent::Page(c, s)));
+            }
+        }
+        extents.sort_by_key(|(addr, _)| *addr);
+
+        // Extents are visited in address order and only ever move down, so copying
+        // each one in turn never overwrites data that has not been moved yet.
+        // Every region is compacted towards its own base and ends in a single free block.
+        self.free_blocks.clear();
+        for region in self.regions.clone() {
+            let mut last_start_addr = region.base;
+            for (start_addr, extent) in extents.iter().filter(|(a, _)| region.contains(*a)) {
+                let size = match *extent {
+                    Extent::Used(i) => {
+                        let block = self.used_blocks.get_mut(i);
+                        block.relocate(last_start_addr);
+                        block.size
+                    }
+                    Extent::Page(c, s) => {
+                        self.slabs[c].slabs[s].start_addr = last_start_addr;
+                        self.slabs[c].page_size
+                    }
+                };
+                let (from, to) = (
+                    region.offset_of(*start_addr),
+                    region.offset_of(last_start_addr),
+                );
+                self.data.copy_within(from..from + size.0, to);
+                last_start_addr = last_start_addr + size;
+            }
+            if last_start_addr < region.end() {
+                let f_mem = Size(region.end().0 - last_start_addr.0);
+                self.free_blocks
+                    .add(Block::new_free(f_mem, last_start_addr));
+            }
+        }
+        self.trim();
         Result::Ok
     }
 
@@ -178,9 +787,77 @@ impl Memory {
 
         out.push_str(&format!("Fragmentation:\n{}\n", self.fragmentation()));
 
+        if self.regions.len() > 1 {
+            out.push_str("Regions:\n");
+            for region in self.regions.iter() {
+                let free: Vec<&Block> = self
+                    .free_blocks
+                    .iter()
+                    .filter(|b| region.contains(b.start_addr))
+                    .collect();
+                out.push_str(&format!(
+                    "{};{};{};{}\n",
+                    region.base.0,
+                    region.size.0,
+                    free.iter().fold(0, |acc, b| acc + b.size.0),
+                    fragmentation(free)
+                ));
+            }
+        }
+
+        if !self.slabs.is_empty() {
+            out.push_str("Slabs:\n");
+            for cache in self.slabs.iter() {
+                out.push_str(&format!("{}\n", cache));
+            }
+        }
+
+        if self.growth.is_some() {
+            out.push_str("Heap:\n");
+            for event in self.heap_events.iter() {
+                out.push_str(&format!("{}\n", event));
+            }
+            if self.heap_events.is_empty() {
+                out.push_str("None\n");
+            }
+        }
+
+        if !self.gc.collections.is_empty() {
+            out.push_str("Collections:\n");
+            for collection in self.gc.collections.iter() {
+                out.push_str(&format!("{}\n", collection));
+            }
+        }
+
+        if !self.processes.is_empty() {
+            out.push_str("Processes:\n");
+            for (pid, quota) in self.processes.quotas.iter() {
+                let blocks = self.processes.blocks(*pid).len();
+                out.push_str(&format!("{};{};{}", pid, blocks, self.process_memory(*pid)));
+                match quota {
+                    Some(quota) => out.push_str(&format!(";{}\n", quota)),
+                    None => out.push('\n'),
+                }
+            }
+        }
+
+        if !self.refcounts.is_empty() {
+            out.push_str("Live:\n");
+            for (id, count) in self.refcounts.iter() {
+                out.push_str(&format!("{};{}\n", id.0, count));
+            }
+        }
+
+        if let Some(stats) = &self.workload {
+            out.push_str(&format!("Workload:\n{}\n", stats));
+        }
+
         out.push_str("Errors:\n");
         for err in self.errors.iter() {
             out.push_str(&format!("{}\n", err));
+            if let (true, Result::DeallocErr(id, _, reason)) = (self.verbose, err) {
+                out.push_str(&format!("# {}\n", reason.explain(*id)));
+            }
         }
         if self.errors.is_empty() {
             out.push_str("None\n");
@@ -191,12 +868,25 @@ impl Memory {
     }
 
     fn fragmentation(&self) -> f64 {
-        let largest_block = self.free_blocks.iter().max_by_key(|b| b.size).unwrap().size;
-        let free_memory = self.get_free_memory();
-        1f64 - largest_block.0 as f64 / free_memory as f64
+        fragmentation(self.free_blocks.iter())
     }
 
     fn incr(&mut self) {
         self.instr_cnt += 1;
     }
 }
+
+fn fragmentation<'a, I: IntoIterator<Item = &'a Block>>(free_blocks: I) -> f64 {
+    let (largest_block, free_memory) =
+        free_blocks.into_iter().fold((0, 0), |(largest, free), b| {
+            (max(largest, b.size.0), free + b.size.0)
+        });
+    match free_memory {
+        0 => 0f64,
+        free_memory => 1f64 - largest_block as f64 / free_memory as f64,
+    }
+}
+
+fn pattern_byte(pattern: u8, offset: usize) -> u8 {
+    pattern.wrapping_add(offset as u8)
+}
@@ -0,0 +1,448 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::{Error, ErrorKind},
+    str::FromStr,
+};
+
+use crate::{
+    block::{Address, Size},
+    tlb::{Latency, Tlb},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Replacement {
+    Fifo,
+    Lru,
+    Opt,
+    Clock,
+    SecondChance,
+}
+
+impl Replacement {
+    pub const ALL: [Replacement; 5] = [
+        Replacement::Fifo,
+        Replacement::Lru,
+        Replacement::Opt,
+        Replacement::Clock,
+        Replacement::SecondChance,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Replacement::Fifo => "FIFO",
+            Replacement::Lru => "LRU",
+            Replacement::Opt => "OPT",
+            Replacement::Clock => "Clock",
+            Replacement::SecondChance => "SecondChance",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reference {
+    pub pid: usize,
+    pub addr: Address,
+}
+
+// A reference is `pid;address`, or just `address` for process 0.
+impl FromStr for Reference {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split(';').map(|f| f.trim()).collect();
+        match fields.as_slice() {
+            [addr] => Ok(Reference {
+                pid: 0,
+                addr: Address(addr.parse()?),
+            }),
+            [pid, addr] => Ok(Reference {
+                pid: pid.parse()?,
+                addr: Address(addr.parse()?),
+            }),
+            _ => Err("".parse::<usize>().unwrap_err()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ReferenceString {
+    pub frames: usize,
+    pub page_size: Size,
+    pub tlb: Option<Tlb>,
+    pub latency: Option<Latency>,
+    pub refs: Vec<Reference>,
+}
+
+// The first line is `frames;page size`, every following line one reference. A `T;entries;ways;policy`
+// line puts a TLB in front of the page tables, an `L;tlb;memory;fault` line sets the latencies.
+impl FromStr for ReferenceString {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |e: std::num::ParseIntError| Error::new(ErrorKind::InvalidData, e);
+        let mut lines = s.lines().filter(|l| !l.trim().is_empty());
+        let mut header = lines
+            .next()
+            .unwrap_or("")
+            .split(';')
+            .map(|v| v.trim().parse());
+        let mut next = || header.next().unwrap_or_else(|| "".parse()).map_err(invalid);
+        let (frames, page_size) = (next()?, Size(next()?));
+        if frames == 0 || page_size.0 == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "The number of frames and the page size have to be positive",
+            ));
+        }
+        let mut refs = ReferenceString {
+            frames,
+            page_size,
+            tlb: None,
+            latency: None,
+            refs: vec![],
+        };
+        for line in lines {
+            let mut fields = line.split(';').skip(1).map(|v| v.trim());
+            let mut next = || fields.next().unwrap_or("").parse().map_err(invalid);
+            if line.starts_with("T;") {
+                let (entries, ways) = (next()?, next()?);
+                let policy = line
+                    .split(';')
+                    .nth(3)
+                    .unwrap_or("lru")
+                    .trim()
+                    .parse()
+                    .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+                if ways == 0 || entries == 0 || entries % ways != 0 {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        "The TLB entries have to be a positive multiple of its ways",
+                    ));
+                }
+                refs.tlb = Some(Tlb::new(entries, ways, policy));
+            } else if line.starts_with("L;") {
+                refs.latency = Some(Latency {
+                    tlb: next()?,
+                    memory: next()?,
+                    fault: next()?,
+                });
+            } else {
+                refs.refs.push(line.parse().map_err(invalid)?);
+            }
+        }
+        Ok(refs)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    pub pid: usize,
+    pub nr: usize,
+}
+
+impl std::fmt::Display for Page {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.pid, self.nr)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Frame {
+    page: Option<Page>,
+    last_used: usize,
+    referenced: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct PageTable {
+    pub entries: BTreeMap<usize, usize>,
+    pub references: usize,
+    pub faults: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    TlbHit,
+    Hit,
+    Fault,
+}
+
+pub struct Step {
+    pub reference: Reference,
+    pub physical: Address,
+    pub access: Access,
+    pub frames: Vec<Option<Page>>,
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let frames = self
+            .frames
+            .iter()
+            .map(|p| match p {
+                Some(page) => page.to_string(),
+                None => "-".to_owned(),
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+        let kind = match self.access {
+            Access::TlbHit => "T",
+            Access::Hit => "H",
+            Access::Fault => "F",
+        };
+        write!(
+            f,
+            "{};{};{};{};{}",
+            self.reference.pid, self.reference.addr.0, self.physical.0, kind, frames
+        )
+    }
+}
+
+pub struct Paging {
+    pub policy: Replacement,
+    pub page_size: Size,
+    frames: Vec<Frame>,
+    pub tables: BTreeMap<usize, PageTable>,
+    // Frames in the order their pages were loaded, used by FIFO and second chance.
+    loaded: VecDeque<usize>,
+    hand: usize,
+    time: usize,
+    pub tlb: Option<Tlb>,
+    pub latency: Option<Latency>,
+    elapsed: usize,
+    pub history: Vec<Step>,
+}
+
+impl Paging {
+    pub fn new(frames: usize, page_size: Size, policy: Replacement) -> Paging {
+        Paging {
+            policy,
+            page_size,
+            frames: vec![Frame::default(); frames],
+            tables: BTreeMap::new(),
+            loaded: VecDeque::new(),
+            hand: 0,
+            time: 0,
+            tlb: None,
+            latency: None,
+            elapsed: 0,
+            history: vec![],
+        }
+    }
+
+    pub fn run(refs: &ReferenceString, policy: Replacement) -> Paging {
+        let mut paging = Paging::new(refs.frames, refs.page_size, policy);
+        paging.tlb = refs.tlb.clone();
+        paging.latency = refs.latency;
+        for (i, reference) in refs.refs.iter().enumerate() {
+            paging.access(*reference, &refs.refs[i + 1..]);
+        }
+        paging
+    }
+
+    pub fn page_of(&self, reference: Reference) -> Page {
+        Page {
+            pid: reference.pid,
+            nr: reference.addr.0 / self.page_size.0,
+        }
+    }
+
+    pub fn frame_addr(&self, frame: usize) -> Address {
+        Address(frame * self.page_size.0)
+    }
+
+    pub fn frames(&self) -> Vec<Option<Page>> {
+        self.frames.iter().map(|f| f.page).collect()
+    }
+
+    // Translates a virtual address to a physical one, loading its page on a fault.
+    // `future` holds the references still to come, only OPT looks at it.
+    pub fn access(&mut self, reference: Reference, future: &[Reference]) -> Address {
+        let (frame_addr, access) = self.translate(reference, future);
+        if let Some(latency) = self.latency {
+            self.elapsed += self.cost(latency, access);
+        }
+        self.record(reference, frame_addr, access)
+    }
+
+    // The TLB is asked first, only on a miss is the page table walked.
+    fn translate(&mut self, reference: Reference, future: &[Reference]) -> (Address, Access) {
+        self.time += 1;
+        let page = self.page_of(reference);
+        self.tables.entry(page.pid).or_default().references += 1;
+        let (frame_addr, access) = match self.tlb.as_mut().and_then(|tlb| tlb.lookup(page)) {
+            Some(frame_addr) => (frame_addr, Access::TlbHit),
+            None => {
+                let table = self.tables.get_mut(&page.pid).unwrap();
+                let (frame, access) = match table.entries.get(&page.nr).copied() {
+                    Some(frame) => (frame, Access::Hit),
+                    None => {
+                        table.faults += 1;
+                        (self.load(page, future), Access::Fault)
+                    }
+                };
+                let frame_addr = self.frame_addr(frame);
+                if let Some(tlb) = self.tlb.as_mut() {
+                    tlb.insert(page, frame_addr);
+                }
+                (frame_addr, access)
+            }
+        };
+        let frame = &mut self.frames[frame_addr.0 / self.page_size.0];
+        frame.last_used = self.time;
+        frame.referenced = true;
+        (frame_addr, access)
+    }
+
+    // Without a TLB there is no lookup to pay for, but every access walks the page table.
+    fn cost(&self, latency: Latency, access: Access) -> usize {
+        let lookup = match self.tlb {
+            Some(_) => latency.tlb,
+            None => 0,
+        };
+        match access {
+            Access::TlbHit => lookup + latency.memory,
+            Access::Hit => lookup + 2 * latency.memory,
+            Access::Fault => lookup + 2 * latency.memory + latency.fault,
+        }
+    }
+
+    fn record(&mut self, reference: Reference, frame_addr: Address, access: Access) -> Address {
+        let physical = frame_addr + reference.addr.0 % self.page_size.0;
+        self.history.push(Step {
+            reference,
+            physical,
+            access,
+            frames: self.frames(),
+        });
+        physical
+    }
+
+    fn load(&mut self, page: Page, future: &[Reference]) -> usize {
+        let frame = match self.frames.iter().position(|f| f.page.is_none()) {
+            Some(frame) => frame,
+            None => {
+                let victim = self.victim(future);
+                let old = self.frames[victim].page.unwrap();
+                self.tables
+                    .get_mut(&old.pid)
+                    .unwrap()
+                    .entries
+                    .remove(&old.nr);
+                if let Some(tlb) = self.tlb.as_mut() {
+                    tlb.invalidate(old);
+                }
+                victim
+            }
+        };
+        self.frames[frame] = Frame {
+            page: Some(page),
+            ..Frame::default()
+        };
+        self.tables
+            .entry(page.pid)
+            .or_default()
+            .entries
+            .insert(page.nr, frame);
+        if let Replacement::Fifo | Replacement::SecondChance = self.policy {
+            self.loaded.push_back(frame);
+        }
+        frame
+    }
+
+    // Only called once every frame holds a page.
+    fn victim(&mut self, future: &[Reference]) -> usize {
+        match self.policy {
+            Replacement::Fifo => self.loaded.pop_front().unwrap(),
+            Replacement::Lru => (0..self.frames.len())
+                .min_by_key(|f| self.frames[*f].last_used)
+                .unwrap(),
+            // The page used furthest in the future, or never again, goes first.
+            Replacement::Opt => (0..self.frames.len())
+                .min_by_key(|f| {
+                    let page = self.frames[*f].page;
+                    let next_use = future
+                        .iter()
+                        .position(|r| Some(self.page_of(*r)) == page)
+                        .unwrap_or(usize::MAX);
+                    std::cmp::Reverse(next_use)
+                })
+                .unwrap(),
+            Replacement::Clock => loop {
+                let frame = self.hand;
+                self.hand = (self.hand + 1) % self.frames.len();
+                if !self.frames[frame].referenced {
+                    break frame;
+                }
+                self.frames[frame].referenced = false;
+            },
+            Replacement::SecondChance => loop {
+                let frame = self.loaded.pop_front().unwrap();
+                if !self.frames[frame].referenced {
+                    break frame;
+                }
+                self.frames[frame].referenced = false;
+                self.loaded.push_back(frame);
+            },
+        }
+    }
+
+    pub fn faults(&self) -> usize {
+        self.tables.values().fold(0, |acc, t| acc + t.faults)
+    }
+
+    pub fn references(&self) -> usize {
+        self.tables.values().fold(0, |acc, t| acc + t.references)
+    }
+
+    pub fn hit_ratio(&self) -> f64 {
+        match self.references() {
+            0 => 0f64,
+            references => (references - self.faults()) as f64 / references as f64,
+        }
+    }
+
+    // The effective memory access time, averaged over all references.
+    pub fn access_time(&self) -> f64 {
+        match self.references() {
+            0 => 0f64,
+            references => self.elapsed as f64 / references as f64,
+        }
+    }
+
+    pub fn output(&self) -> String {
+        let mut out = format!(
+            "{}\nFrames:\n{}\nPage size:\n{}\n",
+            self.policy.name(),
+            self.frames.len(),
+            self.page_size.0
+        );
+        out.push_str(&format!("Page faults:\n{}\n", self.faults()));
+        out.push_str(&format!("Hit ratio:\n{}\n", self.hit_ratio()));
+        if let Some(tlb) = &self.tlb {
+            out.push_str(&format!("TLB:\n{}\n", tlb));
+        }
+        if self.latency.is_some() {
+            out.push_str(&format!("Access time:\n{}\n", self.access_time()));
+        }
+
+        out.push_str("Processes:\n");
+        for (pid, table) in self.tables.iter() {
+            out.push_str(&format!(
+                "{};{};{};{}\n",
+                pid,
+                table.references,
+                table.faults,
+                table.entries.len()
+            ));
+        }
+
+        out.push_str("Frame contents:\n");
+        for step in self.history.iter() {
+            out.push_str(&format!("{}\n", step));
+        }
+        out += "\n";
+
+        out
+    }
+}
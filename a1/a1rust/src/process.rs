@@ -0,0 +1,48 @@
+use std::collections::BTreeMap;
+
+use crate::block::Id;
+
+#[derive(Default)]
+pub struct Processes {
+    pub owners: BTreeMap<Id, usize>,
+    pub quotas: BTreeMap<usize, Option<usize>>,
+}
+
+impl Processes {
+    pub fn new() -> Processes {
+        Processes::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.quotas.is_empty()
+    }
+
+    pub fn set_quota(&mut self, pid: usize, quota: usize) {
+        self.quotas.insert(pid, Some(quota));
+    }
+
+    pub fn quota(&self, pid: usize) -> Option<usize> {
+        self.quotas.get(&pid).copied().flatten()
+    }
+
+    pub fn own(&mut self, id: Id, pid: usize) {
+        self.owners.insert(id, pid);
+        self.quotas.entry(pid).or_insert(None);
+    }
+
+    pub fn owner(&self, id: Id) -> Option<usize> {
+        self.owners.get(&id).copied()
+    }
+
+    pub fn forget(&mut self, id: Id) {
+        self.owners.remove(&id);
+    }
+
+    pub fn blocks(&self, pid: usize) -> Vec<Id> {
+        self.owners
+            .iter()
+            .filter(|(_, owner)| **owner == pid)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+}
@@ -0,0 +1,33 @@
+use crate::block::{Address, Size};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Region {
+    pub base: Address,
+    pub size: Size,
+    pub init_size: Size,
+    // Where the region starts in the data buffer of the memory.
+    pub offset: usize,
+}
+
+impl Region {
+    pub fn new(base: Address, size: Size, offset: usize) -> Region {
+        Region {
+            base,
+            size,
+            init_size: size,
+            offset,
+        }
+    }
+
+    pub fn end(&self) -> Address {
+        self.base + self.size
+    }
+
+    pub fn contains(&self, addr: Address) -> bool {
+        self.base <= addr && addr < self.end()
+    }
+
+    pub fn offset_of(&self, addr: Address) -> usize {
+        self.offset + addr.0 - self.base.0
+    }
+}
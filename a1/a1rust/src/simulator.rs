@@ -0,0 +1,198 @@
+use std::{
+    io::{BufRead, Write},
+    str::FromStr,
+};
+
+use crate::{algos, cmd, lifecycle::DuplicatePolicy, memory, paging, workload};
+
+#[derive(Default)]
+pub struct Options {
+    pub paging: bool,
+    pub timed: bool,
+    pub queue: bool,
+    pub verbose: bool,
+    pub duplicates: DuplicatePolicy,
+    pub size: Option<usize>,
+}
+
+impl Options {
+    pub fn from_args(args: &[String]) -> Options {
+        Options {
+            paging: args.iter().any(|a| a == "--paging"),
+            timed: args.iter().any(|a| a == "--timed"),
+            queue: args.iter().any(|a| a == "--queue"),
+            verbose: args.iter().any(|a| a == "--verbose"),
+            duplicates: args
+                .iter()
+                .find_map(|a| a.strip_prefix("--duplicates="))
+                .map(|p| p.parse().unwrap())
+                .unwrap_or_default(),
+            size: args
+                .iter()
+                .find_map(|a| a.strip_prefix("--size="))
+                .map(|s| s.parse().unwrap()),
+        }
+    }
+
+    pub fn memory(&self, regions: &[(usize, usize)]) -> memory::Memory {
+        let mut memory = memory::Memory::with_regions(regions);
+        memory.verbose = self.verbose;
+        memory.duplicates = self.duplicates;
+        memory
+    }
+}
+
+// Runs the simulation described by the contents of an input file and returns what
+// goes into `<path>.out`. `O` commands write their files next to `path` as a side effect.
+pub fn run(data: &str, options: &Options, path: &str) -> String {
+    if options.paging {
+        return run_paging(data);
+    }
+
+    let fns = algos();
+    let partitions = match options.timed {
+        true => run_workload(data, &fns, options, path),
+        false => run_trace(data, &fns, options, path),
+    };
+
+    partitions
+        .iter()
+        .map(|(n, p)| p.output(n))
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+fn algos() -> Vec<(&'static str, algos::Algo)> {
+    vec![
+        ("FirstFit", algos::first_fit),
+        ("BestFit", algos::best_fit),
+        ("WorstFit", algos::worst_fit),
+    ]
+}
+
+// Runs a trace as it is read, every algorithm executing each command before the next line
+// is read, so the trace is never held in memory. Errors are written as `algo;error` as they
+// happen and `O` writes the current output of every algorithm instead of a file. The final
+// output of every algorithm follows at the end. The size comes from `options.size` or,
+// without it, from the first line.
+pub fn stream<R: BufRead, W: Write>(
+    input: R,
+    out: &mut W,
+    options: &Options,
+) -> std::io::Result<()> {
+    let invalid = |msg: String| std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
+    let mut lines = input.lines();
+    let size = match options.size {
+        Some(size) => size,
+        None => {
+            let line = lines
+                .next()
+                .ok_or_else(|| invalid("The trace is empty".to_owned()))??;
+            line.trim()
+                .parse()
+                .map_err(|_| invalid(format!("{} is not a memory size", line)))?
+        }
+    };
+
+    let mut cmds = cmd::CmdVec::new(size);
+    let mut partitions: Vec<(&str, algos::Algo, memory::Memory)> = vec![];
+    let start = |cmds: &cmd::CmdVec| {
+        algos()
+            .into_iter()
+            .map(|(name, algo)| {
+                let mut memory = options.memory(&cmds.regions);
+                memory.order = cmds.order.clone();
+                (name, algo, memory)
+            })
+            .collect::<Vec<_>>()
+    };
+    for line in lines {
+        let line = line?;
+        let started = !partitions.is_empty();
+        let cmd = match cmds.parse_line(&line)? {
+            Some(cmd) => cmd,
+            None if started => {
+                return Err(invalid(format!(
+                    "{} has to come before the first command",
+                    line
+                )))
+            }
+            None => continue,
+        };
+        // Regions and order are only known once the first command arrives.
+        if !started {
+            partitions = start(&cmds);
+        }
+        for (name, algo, memory) in partitions.iter_mut() {
+            if cmd == cmd::Cmd::Output {
+                write!(out, "{}", memory.checkpoint(name))?;
+                continue;
+            }
+            let errors = memory.errors().len();
+            memory.exec(&cmd, (name, *algo), "");
+            for err in memory.errors()[errors..].iter() {
+                writeln!(out, "{};{}", name, err)?;
+            }
+        }
+        out.flush()?;
+    }
+
+    if partitions.is_empty() {
+        partitions = start(&cmds);
+    }
+    for (name, _, memory) in partitions.iter_mut() {
+        memory.finish();
+        write!(out, "{}", memory.output(name))?;
+    }
+    out.flush()
+}
+
+fn run_trace<'a>(
+    data: &str,
+    fns: &[(&'a str, algos::Algo)],
+    options: &Options,
+    path: &str,
+) -> Vec<(&'a str, memory::Memory)> {
+    let cmds = cmd::CmdVec::from_str(data).unwrap();
+    let mut partitions: Vec<(&str, memory::Memory)> = vec![];
+    let mut memory = options.memory(&cmds.regions);
+    memory.order = cmds.order.clone();
+    for (name, algo) in fns.iter() {
+        let mut mem = memory.with_out_count();
+        for cmd in cmds.iter() {
+            mem.exec(cmd, (name, *algo), path);
+        }
+        mem.finish();
+        memory = mem.with_out_count();
+
+        partitions.push((name, mem));
+    }
+    partitions
+}
+
+fn run_workload<'a>(
+    data: &str,
+    fns: &[(&'a str, algos::Algo)],
+    options: &Options,
+    path: &str,
+) -> Vec<(&'a str, memory::Memory)> {
+    let workload = workload::Workload::from_str(data).unwrap();
+    let mut partitions: Vec<(&str, memory::Memory)> = vec![];
+    for (name, algo) in fns.iter() {
+        let mut mem = options.memory(&[(0, workload.size)]);
+        let stats = workload::simulate(&workload, &mut mem, (name, *algo), options.queue, path);
+        mem.workload = Some(stats);
+
+        partitions.push((name, mem));
+    }
+    partitions
+}
+
+fn run_paging(data: &str) -> String {
+    let refs = paging::ReferenceString::from_str(data).unwrap();
+    paging::Replacement::ALL
+        .iter()
+        .map(|policy| paging::Paging::run(&refs, *policy).output())
+        .collect::<Vec<String>>()
+        .join("")
+}
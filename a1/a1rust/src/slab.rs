@@ -0,0 +1,156 @@
+use crate::block::{Address, Id, Size};
+
+pub struct Slab {
+    pub start_addr: Address,
+    pub objects: Vec<Option<Id>>,
+}
+
+impl Slab {
+    pub fn new(start_addr: Address, capacity: usize) -> Slab {
+        Slab {
+            start_addr,
+            objects: vec![None; capacity],
+        }
+    }
+
+    pub fn used(&self) -> usize {
+        self.objects.iter().filter(|o| o.is_some()).count()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.used() == self.capacity()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.used() == 0
+    }
+
+    pub fn is_partial(&self) -> bool {
+        !self.is_full() && !self.is_empty()
+    }
+
+    pub fn contains(&self, id: Id) -> bool {
+        self.objects.contains(&Some(id))
+    }
+
+    fn insert(&mut self, id: Id) -> bool {
+        match self.objects.iter_mut().find(|o| o.is_none()) {
+            Some(slot) => {
+                *slot = Some(id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn remove(&mut self, id: Id) -> bool {
+        match self.objects.iter_mut().find(|o| **o == Some(id)) {
+            Some(slot) => {
+                *slot = None;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+pub struct SlabCache {
+    pub obj_size: Size,
+    pub page_size: Size,
+    pub slabs: Vec<Slab>,
+}
+
+impl SlabCache {
+    pub fn new(obj_size: Size, page_size: Size) -> SlabCache {
+        SlabCache {
+            obj_size,
+            page_size,
+            slabs: vec![],
+        }
+    }
+
+    pub fn per_page(&self) -> usize {
+        self.page_size.0.checked_div(self.obj_size.0).unwrap_or(0)
+    }
+
+    // Prefer the fullest slab that still has room so partial slabs fill up first.
+    pub fn alloc(&mut self, id: Id) -> bool {
+        let slab = self
+            .slabs
+            .iter_mut()
+            .filter(|s| !s.is_full())
+            .max_by_key(|s| s.used());
+        match slab {
+            Some(slab) => slab.insert(id),
+            None => false,
+        }
+    }
+
+    pub fn add_page(&mut self, start_addr: Address) {
+        let capacity = self.per_page();
+        self.slabs.push(Slab::new(start_addr, capacity));
+    }
+
+    pub fn contains(&self, id: Id) -> bool {
+        self.slabs.iter().any(|s| s.contains(id))
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = Id> + '_ {
+        self.slabs
+            .iter()
+            .flat_map(|s| s.objects.iter().flatten().copied())
+    }
+
+    pub fn locate(&self, id: Id) -> Option<Address> {
+        self.slabs.iter().find_map(|s| {
+            let slot = s.objects.iter().position(|o| *o == Some(id))?;
+            Some(s.start_addr + self.obj_size.0 * slot)
+        })
+    }
+
+    // Returns the page address if the slab became empty, so it can go back to `Memory`.
+    pub fn dealloc(&mut self, id: Id) -> Option<Address> {
+        let i = self.slabs.iter().position(|s| s.contains(id))?;
+        self.slabs[i].remove(id);
+        if self.slabs[i].is_empty() {
+            return Some(self.slabs.remove(i).start_addr);
+        }
+        None
+    }
+
+    pub fn used(&self) -> usize {
+        self.slabs.iter().fold(0, |acc, s| acc + s.used())
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slabs.iter().fold(0, |acc, s| acc + s.capacity())
+    }
+
+    pub fn partial(&self) -> usize {
+        self.slabs.iter().filter(|s| s.is_partial()).count()
+    }
+
+    pub fn utilization(&self) -> f64 {
+        match self.capacity() {
+            0 => 0f64,
+            capacity => self.used() as f64 / capacity as f64,
+        }
+    }
+}
+
+impl std::fmt::Display for SlabCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{};{};{};{}",
+            self.obj_size.0,
+            self.slabs.len(),
+            self.partial(),
+            self.utilization()
+        )
+    }
+}
@@ -0,0 +1,145 @@
+use std::str::FromStr;
+
+use crate::{block::Address, paging::Page};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlbPolicy {
+    Fifo,
+    Lru,
+}
+
+impl FromStr for TlbPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fifo" => Ok(TlbPolicy::Fifo),
+            "lru" => Ok(TlbPolicy::Lru),
+            _ => Err(format!("Unknown TLB policy {}, use fifo or lru", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for TlbPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TlbPolicy::Fifo => write!(f, "fifo"),
+            TlbPolicy::Lru => write!(f, "lru"),
+        }
+    }
+}
+
+// Latencies in arbitrary time units: a TLB lookup, one memory access and servicing a page fault.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Latency {
+    pub tlb: usize,
+    pub memory: usize,
+    pub fault: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    page: Page,
+    frame: Address,
+    loaded: usize,
+    last_used: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Tlb {
+    pub entries: usize,
+    pub ways: usize,
+    pub policy: TlbPolicy,
+    sets: Vec<Vec<Entry>>,
+    time: usize,
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl Tlb {
+    // `entries` has to be a multiple of `ways`, a single set makes the TLB fully associative.
+    pub fn new(entries: usize, ways: usize, policy: TlbPolicy) -> Tlb {
+        Tlb {
+            entries,
+            ways,
+            policy,
+            sets: vec![vec![]; entries / ways],
+            time: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    fn set(&self, page: Page) -> usize {
+        page.nr % self.sets.len()
+    }
+
+    // Returns the base address of the frame holding `page`, if the TLB knows it.
+    pub fn lookup(&mut self, page: Page) -> Option<Address> {
+        self.time += 1;
+        let set = self.set(page);
+        match self.sets[set].iter_mut().find(|e| e.page == page) {
+            Some(entry) => {
+                entry.last_used = self.time;
+                self.hits += 1;
+                Some(entry.frame)
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, page: Page, frame: Address) {
+        let set = self.set(page);
+        let entry = Entry {
+            page,
+            frame,
+            loaded: self.time,
+            last_used: self.time,
+        };
+        let ways = self.ways;
+        let policy = self.policy;
+        let entries = &mut self.sets[set];
+        if entries.len() < ways {
+            entries.push(entry);
+            return;
+        }
+        let victim = (0..entries.len())
+            .min_by_key(|i| match policy {
+                TlbPolicy::Fifo => entries[*i].loaded,
+                TlbPolicy::Lru => entries[*i].last_used,
+            })
+            .unwrap();
+        entries[victim] = entry;
+    }
+
+    // Called when the page loses its frame, so the TLB never hands out a stale translation.
+    pub fn invalidate(&mut self, page: Page) {
+        let set = self.set(page);
+        self.sets[set].retain(|e| e.page != page);
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0f64,
+            lookups => self.hits as f64 / lookups as f64,
+        }
+    }
+}
+
+impl std::fmt::Display for Tlb {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{};{};{};{};{};{}",
+            self.entries,
+            self.ways,
+            self.policy,
+            self.hits,
+            self.misses,
+            self.hit_rate()
+        )
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    collections::{BTreeSet, VecDeque},
+    str::FromStr,
+};
+
+use crate::{algos::Algo, block::Id, cmd::Cmd, memory::Memory};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Job {
+    pub id: usize,
+    pub size: usize,
+    pub arrival: usize,
+    pub duration: usize,
+}
+
+impl FromStr for Job {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut iter = s.split(';').map(|v| v.trim().parse::<usize>());
+        let mut next = || iter.next().unwrap_or_else(|| "".parse());
+        Ok(Job {
+            id: next()?,
+            size: next()?,
+            arrival: next()?,
+            duration: next()?,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Workload {
+    pub size: usize,
+    pub jobs: Vec<Job>,
+}
+
+impl FromStr for Workload {
+    type Err = std::io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |e: std::num::ParseIntError| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+        };
+        let mut lines = s.lines().filter(|l| !l.trim().is_empty());
+        let size = lines.next().unwrap_or("").trim().parse().map_err(invalid)?;
+        let mut jobs = lines
+            .map(|l| l.parse().map_err(invalid))
+            .collect::<Result<Vec<Job>, _>>()?;
+        // A job is freed by its id, so two jobs sharing one could free each other's block.
+        let mut ids = BTreeSet::new();
+        if let Some(job) = jobs.iter().find(|j| !ids.insert(j.id)) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Job id {} is used more than once", job.id),
+            ));
+        }
+        jobs.sort_by_key(|j| j.arrival);
+        Ok(Workload { size, jobs })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Stats {
+    pub completed: usize,
+    pub rejected: usize,
+    pub total_wait: usize,
+    pub end_time: usize,
+}
+
+impl Stats {
+    pub fn mean_wait(&self) -> f64 {
+        match self.completed {
+            0 => 0f64,
+            completed => self.total_wait as f64 / completed as f64,
+        }
+    }
+
+    pub fn throughput(&self) -> f64 {
+        match self.end_time {
+            0 => 0f64,
+            end_time => self.completed as f64 / end_time as f64,
+        }
+    }
+}
+
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{};{};{};{}",
+            self.completed,
+            self.rejected,
+            self.mean_wait(),
+            self.throughput()
+        )
+    }
+}
+
+// Advances simulated time from event to event. At every point in time the jobs that are
+// done are freed first, then queued jobs are retried in arrival order, and only then do
+// the new arrivals get their turn. Without `queue` an arrival that does not fit is rejected.
+pub fn simulate(
+    workload: &Workload,
+    memory: &mut Memory,
+    algo: (&str, Algo),
+    queue: bool,
+    path: &str,
+) -> Stats {
+    let mut stats = Stats::default();
+    let mut arrivals: VecDeque<Job> = workload.jobs.iter().copied().collect();
+    let mut waiting: VecDeque<Job> = VecDeque::new();
+    let mut running: Vec<(usize, usize)> = vec![];
+    let mut time = 0;
+
+    loop {
+        let (done, still_running): (Vec<_>, Vec<_>) =
+            running.into_iter().partition(|(end, _)| *end <= time);
+        running = still_running;
+        for (_, id) in done {
+            memory.exec(&Cmd::Dealloc(id), algo, path);
+            stats.completed += 1;
+        }
+
+        let mut retry = std::mem::take(&mut waiting);
+        while let Some(job) = arrivals.front().filter(|j| j.arrival <= time) {
+            retry.push_back(*job);
+            arrivals.pop_front();
+        }
+        for job in retry {
+            memory.exec(&Cmd::Alloc(job.id, job.size, None), algo, path);
+            if memory.locate(Id(job.id)).is_some() {
+                stats.total_wait += time - job.arrival;
+                running.push((time + job.duration.max(1), job.id));
+            } else if queue {
+                waiting.push_back(job);
+            } else {
+                stats.rejected += 1;
+            }
+        }
+
+        let next_departure = running.iter().map(|(end, _)| *end).min();
+        let next_arrival = arrivals.front().map(|j| j.arrival);
+        time = match (next_departure, next_arrival) {
+            (Some(d), Some(a)) => d.min(a),
+            (Some(d), None) => d,
+            (None, Some(a)) => a,
+            (None, None) => break,
+        };
+    }
+
+    // Jobs still waiting once nothing is running anymore can never be placed.
+    stats.rejected += waiting.len();
+    stats.end_time = time;
+    stats
+}
@@ -0,0 +1,31 @@
+mod common;
+
+use a1rust::allocator::{FitAllocator, Policy};
+use common::HEAP_SIZE;
+
+static mut HEAP: [u8; HEAP_SIZE] = [0; HEAP_SIZE];
+
+#[global_allocator]
+static ALLOCATOR: FitAllocator =
+    unsafe { FitAllocator::new(&raw mut HEAP as *mut u8, HEAP_SIZE, Policy::FirstFit) };
+
+#[test]
+fn test_vec_workload() {
+    common::vec_workload(&ALLOCATOR);
+}
+
+#[test]
+fn test_string_workload() {
+    common::string_workload(&ALLOCATOR);
+}
+
+#[test]
+fn test_over_aligned_workload() {
+    common::over_aligned_workload(&ALLOCATOR);
+}
+
+#[test]
+fn test_policy() {
+    assert_eq!(ALLOCATOR.policy(), Policy::FirstFit);
+    assert!(ALLOCATOR.free_memory() < ALLOCATOR.size());
+}
@@ -0,0 +1,74 @@
+use a1rust::{
+    algos,
+    block::{Address, Id},
+    cmd::{Cmd, CmdVec},
+    memory::Memory,
+};
+
+fn run(input: &str) -> Memory {
+    let cmds: CmdVec = input.parse().unwrap();
+    let mut memory = Memory::with_regions(&cmds.regions);
+    for cmd in cmds.iter() {
+        memory.exec(cmd, ("FirstFit", algos::first_fit), "");
+    }
+    memory
+}
+
+fn errors(memory: &Memory) -> Vec<String> {
+    memory.errors().iter().map(|e| e.to_string()).collect()
+}
+
+#[test]
+fn test_compact_keeps_contents() {
+    let memory = run("100\n\
+         A;0;10\nA;1;20\nA;2;30\nA;3;5\n\
+         W;1;11\nW;3;250\n\
+         D;0\nD;2\nC\n\
+         V;1;11\nV;3;250\n");
+    assert!(errors(&memory).is_empty());
+    assert_eq!(memory.locate(Id(1)).unwrap().0, Address(0));
+    assert_eq!(memory.locate(Id(3)).unwrap().0, Address(20));
+
+    let memory = run("100\nA;0;10\nA;1;20\nW;1;11\nD;0\nC\nV;1;12\n");
+    assert_eq!(errors(&memory), ["V;6;1"]);
+}
+
+#[test]
+fn test_compact_keeps_contents_per_region() {
+    let memory = run("50\nM;100;50\nA;0;40\nA;1;20\nA;2;20\nW;2;3\nD;1\nC\nV;2;3\n");
+    assert!(errors(&memory).is_empty());
+    assert_eq!(memory.locate(Id(2)).unwrap().0, Address(100));
+}
+
+#[test]
+fn test_realloc_keeps_contents() {
+    // Growing keeps the written bytes, the new tail holds no pattern to check.
+    let memory = run("100\nA;2;10\nW;2;7\nA;9;1\nR;2;3;20\nV;3;7\n");
+    assert!(errors(&memory).is_empty());
+    assert_eq!(memory.locate(Id(3)).unwrap().0, Address(11));
+
+    // Shrinking keeps the bytes that still fit.
+    let memory = run("100\nA;2;10\nW;2;7\nR;2;3;4\nV;3;7\n");
+    assert!(errors(&memory).is_empty());
+
+    // A new write covers the whole block again.
+    let memory = run("100\nA;2;10\nW;2;7\nR;2;3;20\nW;3;8\nV;3;8\n");
+    assert!(errors(&memory).is_empty());
+
+    let memory = run("100\nA;2;10\nW;2;7\nR;2;3;20\nV;3;8\nV;2;7\n");
+    assert_eq!(errors(&memory), ["V;4;3", "V;5;2"]);
+}
+
+#[test]
+fn test_realloc_onto_itself() {
+    let memory = run("100\nA;2;10\nW;2;7\nR;2;2;20\nV;2;7\n");
+    assert_eq!(errors(&memory), ["R;3;2"]);
+    assert_eq!(memory.live_blocks().len(), 1);
+}
+
+#[test]
+fn test_pattern_range() {
+    assert_eq!("W;1;255".parse::<Cmd>().unwrap(), Cmd::Write(1, 255));
+    assert!("W;1;300".parse::<Cmd>().is_err());
+    assert!("V;1;256".parse::<Cmd>().is_err());
+}
@@ -0,0 +1,110 @@
+use a1rust::{
+    algos,
+    block::{Address, Block, CheckedAdd, CheckedSub, Id, Size},
+    cmd::Cmd,
+    memory::Memory,
+};
+
+macro_rules! assert_primitive {
+    ($for:ident, $($ty:ident),*) => {
+        $(
+            assert_eq!($for(5).checked_add(3 as $ty), Some($for(8)));
+            assert_eq!($for(5).checked_sub(5 as $ty), Some($for(0)));
+            assert_eq!($for(5).checked_sub(6 as $ty), None);
+            assert_eq!($for(usize::MAX - 1).checked_add(1 as $ty), Some($for(usize::MAX)));
+            assert_eq!($for(usize::MAX).checked_add(1 as $ty), None);
+            assert_eq!($for(5) + (3 as $ty), $for(8));
+            assert_eq!($for(5) - (3 as $ty), $for(2));
+        )*
+    };
+}
+
+macro_rules! assert_signed {
+    ($for:ident, $($ty:ident),*) => {
+        $(
+            assert_eq!($for(5).checked_add(-2 as $ty), Some($for(3)));
+            assert_eq!($for(5).checked_add(-6 as $ty), None);
+            assert_eq!($for(5).checked_sub(-2 as $ty), Some($for(7)));
+            assert_eq!($for(usize::MAX).checked_sub(-1 as $ty), None);
+            assert_eq!($for(0).checked_add(<$ty>::MIN), None);
+            assert_eq!($for(5) + (-2 as $ty), $for(3));
+            assert_eq!($for(5) - (-2 as $ty), $for(7));
+        )*
+    };
+}
+
+#[test]
+fn test_newtype_arithmetic() {
+    assert_eq!(Address(5).checked_add(Size(3)), Some(Address(8)));
+    assert_eq!(Address(5).checked_add(Address(3)), Some(Address(8)));
+    assert_eq!(Size(5).checked_add(Size(3)), Some(Size(8)));
+    assert_eq!(Size(5).checked_add(Address(3)), Some(Size(8)));
+    assert_eq!(Address(usize::MAX).checked_add(Size(1)), None);
+    assert_eq!(Address(usize::MAX).checked_add(Address(1)), None);
+    assert_eq!(Size(usize::MAX).checked_add(Size(1)), None);
+    assert_eq!(Size(usize::MAX).checked_add(Address(1)), None);
+    assert_eq!(Address(3).checked_sub(Size(3)), Some(Address(0)));
+    assert_eq!(Address(3).checked_sub(Address(4)), None);
+    assert_eq!(Size(3).checked_sub(Size(4)), None);
+    assert_eq!(Size(3).checked_sub(Address(4)), None);
+}
+
+#[test]
+fn test_primitive_arithmetic() {
+    assert_primitive!(Address, usize, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+    assert_primitive!(Size, usize, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+    assert_signed!(Address, i8, i16, i32, i64, i128);
+    assert_signed!(Size, i8, i16, i32, i64, i128);
+}
+
+#[test]
+fn test_wide_primitives() {
+    assert_eq!(Address(0).checked_add(u128::MAX), None);
+    assert_eq!(Address(0).checked_sub(u128::MAX), None);
+    assert_eq!(Size(usize::MAX).checked_sub(i128::MIN), None);
+    assert_eq!(
+        Size(usize::MAX).checked_add(-(usize::MAX as i128)),
+        Some(Size(0))
+    );
+}
+
+#[test]
+#[should_panic(expected = "attempt to subtract from Address with overflow")]
+fn test_operator_underflow() {
+    let _ = Address(0) - 1usize;
+}
+
+#[test]
+#[should_panic(expected = "attempt to add to Size with overflow")]
+fn test_operator_overflow() {
+    let _ = Size(usize::MAX) + Size(1);
+}
+
+#[test]
+fn test_block_bounds() {
+    assert_eq!(Block::last_addr(Address(0), Size(0)), None);
+    assert_eq!(Block::last_addr(Address(4), Size(0)), None);
+    assert_eq!(Block::last_addr(Address(4), Size(1)), Some(Address(4)));
+    assert_eq!(
+        Block::last_addr(Address(usize::MAX), Size(1)),
+        Some(Address(usize::MAX))
+    );
+    assert_eq!(Block::last_addr(Address(usize::MAX), Size(2)), None);
+    assert_eq!(Block::new_free(Size(1), Address(0)).end_addr, Address(0));
+}
+
+#[test]
+fn test_rejected_sizes() {
+    let mut memory = Memory::new(100);
+    for cmd in [
+        Cmd::Alloc(1, 0, None),
+        Cmd::Alloc(2, usize::MAX, None),
+        Cmd::Alloc(3, 200, None),
+        Cmd::Alloc(4, 100, None),
+    ] {
+        memory.exec(&cmd, ("FirstFit", algos::first_fit), "");
+    }
+    let out = memory.output("FirstFit");
+    assert!(out.contains("Errors:\nSize;1;1\nSize;2;2\nA;3;100\n"));
+    assert!(memory.locate(Id(4)).is_some());
+}
@@ -0,0 +1,62 @@
+use a1rust::allocator::FitAllocator;
+
+pub const HEAP_SIZE: usize = 1 << 23;
+
+pub fn vec_workload(allocator: &FitAllocator) {
+    let mut vecs: Vec<Vec<u64>> = vec![];
+    for i in 0..200 {
+        let mut v = Vec::with_capacity(i % 17);
+        for j in 0..i {
+            v.push((i * j) as u64);
+        }
+        vecs.push(v);
+        if i % 3 == 0 {
+            vecs.remove(i / 6);
+        }
+    }
+    allocator.check_layout().unwrap();
+
+    for (i, v) in vecs.iter().enumerate() {
+        assert!(
+            v.iter()
+                .enumerate()
+                .all(|(j, x)| *x == (v.len() * j) as u64),
+            "vec {}",
+            i
+        );
+    }
+    vecs.retain(|v| v.len() % 2 == 0);
+    vecs.shrink_to_fit();
+    allocator.check_layout().unwrap();
+}
+
+pub fn string_workload(allocator: &FitAllocator) {
+    let mut strings: Vec<String> = vec![];
+    for i in 0..300 {
+        let mut s = String::new();
+        for _ in 0..i % 23 {
+            s.push_str(&i.to_string());
+        }
+        strings.push(s);
+    }
+    strings.retain(|s| s.len() % 3 != 0);
+    allocator.check_layout().unwrap();
+
+    let joined = strings.join(",");
+    assert_eq!(joined.split(',').count(), strings.len());
+    drop(strings);
+    allocator.check_layout().unwrap();
+}
+
+pub fn over_aligned_workload(allocator: &FitAllocator) {
+    #[repr(align(128))]
+    struct Aligned(u8);
+
+    let boxes: Vec<Box<Aligned>> = (0..50).map(|i| Box::new(Aligned(i))).collect();
+    for (i, b) in boxes.iter().enumerate() {
+        assert_eq!(&**b as *const Aligned as usize % 128, 0);
+        assert_eq!(b.0, i as u8);
+    }
+    drop(boxes);
+    allocator.check_layout().unwrap();
+}
@@ -0,0 +1,74 @@
+use a1rust::{
+    algos,
+    block::{Address, Id, Size},
+    cmd::CmdVec,
+    lifecycle::{DuplicatePolicy, IdState},
+    memory::Memory,
+};
+
+fn run(input: &str, duplicates: DuplicatePolicy) -> Memory {
+    let cmds: CmdVec = input.parse().unwrap();
+    let mut memory = Memory::new(cmds.size);
+    memory.duplicates = duplicates;
+    for cmd in cmds.iter() {
+        memory.exec(cmd, ("FirstFit", algos::first_fit), "");
+    }
+    memory
+}
+
+fn errors(memory: &Memory) -> Vec<String> {
+    memory.errors().iter().map(|e| e.to_string()).collect()
+}
+
+const TRACE: &str = "100\nA;1;10\nA;1;20\n";
+
+#[test]
+fn test_reject_duplicate() {
+    let memory = run(TRACE, DuplicatePolicy::Reject);
+    assert_eq!(errors(&memory), ["Dup;2;1"]);
+    assert_eq!(memory.live_blocks(), [(Id(1), Size(10))]);
+
+    let memory = run(&format!("{}D;1\nD;1\n", TRACE), DuplicatePolicy::Reject);
+    assert_eq!(errors(&memory), ["Dup;2;1", "D;4;2"]);
+    assert!(memory.live_blocks().is_empty());
+    assert_eq!(memory.get_free_memory(), 100);
+}
+
+#[test]
+fn test_flag_duplicate() {
+    let memory = run(TRACE, DuplicatePolicy::Flag);
+    assert_eq!(errors(&memory), ["Dup;2;1"]);
+    // The second block replaces the first one instead of living next to it.
+    assert_eq!(memory.live_blocks(), [(Id(1), Size(20))]);
+    assert_eq!(memory.locate(Id(1)), Some((Address(0), Size(20))));
+    assert_eq!(memory.lifecycle.state(Id(1)), IdState::Live);
+
+    let memory = run(&format!("{}D;1\n", TRACE), DuplicatePolicy::Flag);
+    assert_eq!(errors(&memory), ["Dup;2;1"]);
+    assert!(memory.live_blocks().is_empty());
+    assert_eq!(memory.get_free_memory(), 100);
+    memory.check_layout().unwrap();
+}
+
+#[test]
+fn test_realloc_onto_live_id() {
+    let memory = run("100\nA;1;10\nA;2;10\nR;1;2;30\n", DuplicatePolicy::Reject);
+    assert_eq!(errors(&memory), ["Dup;3;2"]);
+    assert_eq!(memory.live_blocks().len(), 2);
+}
+
+#[test]
+fn test_flag_forgets_replaced_block() {
+    // The pattern written to the old block must not be checked against the smaller new one.
+    let memory = run(
+        "20\nA;1;10\nW;1;7\nA;2;8\nA;1;2\nV;1;7\n",
+        DuplicatePolicy::Flag,
+    );
+    assert_eq!(errors(&memory), ["Dup;4;1", "V;5;1"]);
+    assert_eq!(memory.locate(Id(1)), Some((Address(18), Size(2))));
+
+    let memory = run("100\nA;1;10;3\n+;1\nT;1\nA;1;20\n", DuplicatePolicy::Flag);
+    assert!(memory.refcounts.is_empty());
+    assert!(memory.processes.owner(Id(1)).is_none());
+    assert!(memory.gc.roots.is_empty());
+}
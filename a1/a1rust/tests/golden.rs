@@ -0,0 +1,193 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use a1rust::simulator::{self, Options};
+
+// Run with UPDATE_GOLDEN=1 to replace the expected files with the current output.
+const UPDATE: &str = "UPDATE_GOLDEN";
+
+// Every `<name>.in` next to the manifest is a fixture, optionally with the command line
+// flags it needs in `<name>.args`.
+fn fixtures(dir: &Path) -> Vec<PathBuf> {
+    let mut fixtures: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "in"))
+        .collect();
+    fixtures.sort();
+    fixtures
+}
+
+// `<name>.out` and every `<name>.out<n>` in `dir`, by file name.
+fn outputs(dir: &Path, name: &str) -> Vec<String> {
+    let mut outputs: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .filter_map(|entry| entry.unwrap().file_name().into_string().ok())
+        .filter(|file| {
+            file.strip_prefix(name)
+                .and_then(|rest| rest.strip_prefix(".out"))
+                .is_some_and(|n| n.chars().all(|c| c.is_ascii_digit()))
+        })
+        .collect();
+    outputs.sort();
+    outputs
+}
+
+// Splits an output into its sections, each named after the algorithm and the section header.
+fn sections(out: &str) -> Vec<(String, Vec<&str>)> {
+    let mut sections: Vec<(String, Vec<&str>)> = vec![];
+    for block in out.split("\n\n").filter(|b| !b.trim().is_empty()) {
+        let mut lines = block.lines();
+        let algo = lines.next().unwrap_or("");
+        for line in lines {
+            match (line.ends_with(':'), sections.last_mut()) {
+                (false, Some((_, body))) => body.push(line),
+                _ => sections.push((format!("{} / {}", algo, line), vec![])),
+            }
+        }
+    }
+    sections
+}
+
+// A line diff of two section bodies, based on their longest common subsequence.
+fn diff_lines(expected: &[&str], actual: &[&str]) -> String {
+    let (n, m) = (expected.len(), actual.len());
+    let mut lcs = vec![vec![0; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = match expected[i] == actual[j] {
+                true => lcs[i + 1][j + 1] + 1,
+                false => lcs[i + 1][j].max(lcs[i][j + 1]),
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && expected[i] == actual[j] {
+            out.push_str(&format!("  {}\n", expected[i]));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push_str(&format!("- {}\n", expected[i]));
+            i += 1;
+        } else {
+            out.push_str(&format!("+ {}\n", actual[j]));
+            j += 1;
+        }
+    }
+    out
+}
+
+fn diff(expected: &str, actual: &str) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+    let (expected, actual) = (sections(expected), sections(actual));
+    let mut out = String::new();
+    for (name, body) in expected.iter() {
+        match actual.iter().find(|(n, _)| n == name) {
+            Some((_, actual_body)) if actual_body == body => (),
+            Some((_, actual_body)) => {
+                out.push_str(&format!("@@ {}\n{}", name, diff_lines(body, actual_body)))
+            }
+            None => out.push_str(&format!(
+                "@@ {} is missing\n{}",
+                name,
+                diff_lines(body, &[])
+            )),
+        }
+    }
+    for (name, body) in actual.iter() {
+        if !expected.iter().any(|(n, _)| n == name) {
+            out.push_str(&format!("@@ {} is new\n{}", name, diff_lines(&[], body)));
+        }
+    }
+    if out.is_empty() {
+        out.push_str("@@ the outputs only differ in blank lines\n");
+    }
+    Some(out)
+}
+
+// Runs one fixture in a scratch directory, so the files `O` writes never touch the golden ones.
+fn check(fixture: &Path, update: bool) -> Vec<String> {
+    let dir = fixture.parent().unwrap();
+    let name = fixture.file_stem().unwrap().to_str().unwrap();
+    let scratch =
+        std::env::temp_dir().join(format!("a1rust-golden-{}-{}", std::process::id(), name));
+    fs::create_dir_all(&scratch).unwrap();
+    for file in outputs(&scratch, name) {
+        fs::remove_file(scratch.join(file)).unwrap();
+    }
+
+    let args: Vec<String> = fs::read_to_string(dir.join(format!("{}.args", name)))
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(|a| a.to_owned())
+        .collect();
+    let data = fs::read_to_string(fixture).unwrap();
+    let path = scratch.join(name);
+    let out = simulator::run(&data, &Options::from_args(&args), path.to_str().unwrap());
+    fs::write(scratch.join(format!("{}.out", name)), out).unwrap();
+
+    let produced = outputs(&scratch, name);
+    let expected = outputs(dir, name);
+    let mut failures = vec![];
+    for file in produced.iter() {
+        let actual = fs::read_to_string(scratch.join(file)).unwrap();
+        if update {
+            fs::write(dir.join(file), actual).unwrap();
+            continue;
+        }
+        match fs::read_to_string(dir.join(file)) {
+            Ok(golden) => {
+                if let Some(diff) = diff(&golden, &actual) {
+                    failures.push(format!("{} differs:\n{}", file, diff));
+                }
+            }
+            Err(_) => failures.push(format!("{} was produced but has no golden file", file)),
+        }
+    }
+    for file in expected.iter().filter(|f| !produced.contains(f)) {
+        match update {
+            true => fs::remove_file(dir.join(file)).unwrap(),
+            false => failures.push(format!("{} was expected but not produced", file)),
+        }
+    }
+    fs::remove_dir_all(&scratch).unwrap();
+    failures
+}
+
+#[test]
+fn test_golden_files() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let update = std::env::var(UPDATE).is_ok_and(|v| v == "1");
+    let fixtures = fixtures(dir);
+    assert!(
+        !fixtures.is_empty(),
+        "No fixtures found in {}",
+        dir.display()
+    );
+
+    let failures: Vec<String> = fixtures.iter().flat_map(|f| check(f, update)).collect();
+    assert!(
+        failures.is_empty(),
+        "Golden files do not match, rerun with {}=1 to update them:\n\n{}",
+        UPDATE,
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn test_section_diff() {
+    let expected = "FirstFit\nSize:\n10\nFree blocks:\n0;4\n6;9\nErrors:\nNone\n\n";
+    let actual = "FirstFit\nSize:\n10\nFree blocks:\n0;4\n7;9\nErrors:\nNone\n\n";
+    assert_eq!(diff(expected, expected), None);
+    assert_eq!(
+        diff(expected, actual).unwrap(),
+        "@@ FirstFit / Free blocks:\n  0;4\n- 6;9\n+ 7;9\n"
+    );
+}
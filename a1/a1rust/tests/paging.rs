@@ -0,0 +1,101 @@
+use a1rust::{
+    block::{Address, Size},
+    paging::{Access, Page, Paging, Reference, ReferenceString, Replacement},
+    tlb::{Tlb, TlbPolicy},
+};
+
+// The reference string from the textbook, with three frames of 100 bytes.
+fn textbook() -> ReferenceString {
+    let mut input = "3;100\n".to_owned();
+    for page in [7, 0, 1, 2, 0, 3, 0, 4, 2, 3, 0, 3, 2, 1, 2, 0, 1, 7, 0, 1] {
+        input.push_str(&format!("{}\n", page * 100 + 5));
+    }
+    input.parse().unwrap()
+}
+
+#[test]
+fn test_faults() {
+    let refs = textbook();
+    let faults = |policy| Paging::run(&refs, policy).faults();
+    assert_eq!(faults(Replacement::Fifo), 15);
+    assert_eq!(faults(Replacement::Lru), 12);
+    assert_eq!(faults(Replacement::Opt), 9);
+    assert_eq!(faults(Replacement::Clock), 14);
+    assert_eq!(faults(Replacement::SecondChance), 14);
+}
+
+#[test]
+fn test_translation() {
+    let mut paging = Paging::new(2, Size(64), Replacement::Lru);
+    let access = |paging: &mut Paging, pid, addr| {
+        paging.access(
+            Reference {
+                pid,
+                addr: Address(addr),
+            },
+            &[],
+        )
+    };
+    assert_eq!(access(&mut paging, 0, 130), Address(2));
+    assert_eq!(access(&mut paging, 1, 130), Address(66));
+    assert_eq!(access(&mut paging, 0, 129), Address(1));
+    // Process 1 used its page least recently, so it loses its frame.
+    assert_eq!(access(&mut paging, 0, 10), Address(74));
+    assert_eq!(paging.faults(), 3);
+    assert_eq!(paging.tables[&1].entries.len(), 0);
+}
+
+#[test]
+fn test_parse() {
+    let refs: ReferenceString = "4;256\n1;300\n513\n".parse().unwrap();
+    assert_eq!(refs.frames, 4);
+    assert_eq!(
+        refs.refs[0],
+        Reference {
+            pid: 1,
+            addr: Address(300)
+        }
+    );
+    assert_eq!(
+        refs.refs[1],
+        Reference {
+            pid: 0,
+            addr: Address(513)
+        }
+    );
+    assert!("0;256\n1\n".parse::<ReferenceString>().is_err());
+    assert!("4;256\n1;2;3\n".parse::<ReferenceString>().is_err());
+}
+
+#[test]
+fn test_tlb() {
+    let mut tlb = Tlb::new(4, 2, TlbPolicy::Lru);
+    let page = |nr| Page { pid: 0, nr };
+    tlb.insert(page(0), Address(0));
+    tlb.insert(page(2), Address(64));
+    assert_eq!(tlb.lookup(page(0)), Some(Address(0)));
+    // Pages 0, 2 and 4 share a set, page 2 was used least recently.
+    tlb.insert(page(4), Address(128));
+    assert_eq!(tlb.lookup(page(2)), None);
+    assert_eq!(tlb.lookup(page(1)), None);
+    tlb.invalidate(page(0));
+    assert_eq!(tlb.lookup(page(0)), None);
+    assert_eq!(tlb.lookup(page(4)), Some(Address(128)));
+    assert_eq!((tlb.hits, tlb.misses), (2, 3));
+}
+
+#[test]
+fn test_access_time() {
+    let refs: ReferenceString = "2;100\nT;2;1;fifo\nL;1;100;1000\n5\n10\n105\n5\n205\n5\n"
+        .parse()
+        .unwrap();
+    let paging = Paging::run(&refs, Replacement::Lru);
+    let kinds: Vec<Access> = paging.history.iter().map(|s| s.access).collect();
+    use Access::*;
+    assert_eq!(kinds, vec![Fault, TlbHit, Fault, TlbHit, Fault, Hit]);
+    assert_eq!(paging.tlb.as_ref().unwrap().hit_rate(), 2f64 / 6f64);
+    assert_eq!(
+        paging.access_time(),
+        (3 * 1201 + 2 * 101 + 201) as f64 / 6f64
+    );
+}
@@ -0,0 +1,42 @@
+use a1rust::{
+    algos,
+    block::{Id, Size},
+    cmd::CmdVec,
+    memory::Memory,
+};
+
+fn run(input: &str) -> Memory {
+    let cmds: CmdVec = input.parse().unwrap();
+    let mut memory = Memory::new(cmds.size);
+    for cmd in cmds.iter() {
+        memory.exec(cmd, ("FirstFit", algos::first_fit), "");
+    }
+    memory
+}
+
+fn errors(memory: &Memory) -> Vec<String> {
+    memory.errors().iter().map(|e| e.to_string()).collect()
+}
+
+#[test]
+fn test_dealloc_drops_one_reference() {
+    let memory = run("100\nA;1;10\n+;1\n+;1\nD;1\n");
+    assert_eq!(memory.live_blocks(), [(Id(1), Size(10))]);
+    assert_eq!(memory.refcounts.get(&Id(1)), Some(&2));
+
+    let memory = run("100\nA;1;10\n+;1\n+;1\nD;1\n-;1\n-;1\n");
+    assert!(errors(&memory).is_empty());
+    assert!(memory.live_blocks().is_empty());
+    assert_eq!(memory.get_free_memory(), 100);
+
+    let memory = run("100\nA;1;10\n+;1\nD;1\nD;1\nD;1\n");
+    assert_eq!(errors(&memory), ["D;5;2"]);
+}
+
+#[test]
+fn test_kill_frees_retained_blocks() {
+    let memory = run("100\nA;1;10;1\n+;1\n+;1\nK;1\n");
+    assert!(errors(&memory).is_empty());
+    assert!(memory.live_blocks().is_empty());
+    assert!(memory.refcounts.is_empty());
+}
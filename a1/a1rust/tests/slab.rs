@@ -0,0 +1,104 @@
+use a1rust::{
+    algos,
+    block::{Address, Id, Size},
+    cmd::{Cmd, CmdVec},
+    memory::Memory,
+};
+
+fn exec(memory: &mut Memory, cmds: &[Cmd]) {
+    for cmd in cmds {
+        memory.exec(cmd, ("FirstFit", algos::first_fit), "");
+    }
+}
+
+// Four 16 byte objects to a page of 64 bytes.
+fn memory() -> Memory {
+    let mut memory = Memory::new(1000);
+    exec(&mut memory, &[Cmd::Slab(16, 64)]);
+    memory
+}
+
+#[test]
+fn test_slab_alloc() {
+    let mut memory = memory();
+    let cmds: Vec<Cmd> = (0..5).map(|id| Cmd::Alloc(id, 16, None)).collect();
+    exec(&mut memory, &cmds);
+    exec(&mut memory, &[Cmd::Alloc(5, 20, None)]);
+    assert!(memory.errors().is_empty());
+
+    let cache = &memory.slabs[0];
+    assert_eq!(cache.slabs.len(), 2);
+    assert_eq!(cache.used(), 5);
+    assert_eq!(memory.locate(Id(3)), Some((Address(48), Size(16))));
+    assert_eq!(memory.locate(Id(4)), Some((Address(64), Size(16))));
+    // Only the matching size goes to the cache, the rest is carved after its pages.
+    assert_eq!(memory.used_blocks.iter().count(), 1);
+    assert_eq!(memory.locate(Id(5)), Some((Address(128), Size(20))));
+    memory.check_layout().unwrap();
+}
+
+#[test]
+fn test_slab_dealloc() {
+    let mut memory = memory();
+    let cmds: Vec<Cmd> = (0..5).map(|id| Cmd::Alloc(id, 16, None)).collect();
+    exec(&mut memory, &cmds);
+    exec(&mut memory, &[Cmd::Dealloc(4)]);
+    // The second page is empty again and goes back to the memory.
+    assert_eq!(memory.slabs[0].slabs.len(), 1);
+    assert_eq!(memory.get_free_memory(), 1000 - 64);
+
+    exec(&mut memory, &[Cmd::Dealloc(1), Cmd::Dealloc(1)]);
+    assert_eq!(memory.slabs[0].used(), 3);
+    assert_eq!(memory.errors().len(), 1);
+    assert_eq!(memory.errors()[0].to_string(), "D;9;2");
+    memory.check_layout().unwrap();
+}
+
+#[test]
+fn test_slab_utilization() {
+    let mut memory = memory();
+    let cmds: Vec<Cmd> = (0..6).map(|id| Cmd::Alloc(id, 16, None)).collect();
+    exec(&mut memory, &cmds);
+    let cache = &memory.slabs[0];
+    assert_eq!(cache.utilization(), 0.75);
+    assert_eq!(cache.partial(), 1);
+    assert_eq!(cache.to_string(), "16;2;1;0.75");
+
+    exec(&mut memory, &[Cmd::Dealloc(0)]);
+    assert_eq!(memory.slabs[0].partial(), 2);
+    assert!(memory.output("FirstFit").contains("Slabs:\n16;2;2;0.625\n"));
+}
+
+#[test]
+fn test_slab_compact() {
+    let mut memory = memory();
+    exec(
+        &mut memory,
+        &[
+            Cmd::Alloc(0, 100, None),
+            Cmd::Alloc(1, 16, None),
+            Cmd::Write(1, 7),
+            Cmd::Dealloc(0),
+            Cmd::Compact,
+        ],
+    );
+    // The page moves down as a whole and its objects move with it.
+    assert_eq!(memory.slabs[0].slabs[0].start_addr, Address(0));
+    assert_eq!(memory.locate(Id(1)), Some((Address(0), Size(16))));
+    exec(&mut memory, &[Cmd::Verify(1, 7)]);
+    assert!(memory.errors().is_empty());
+    memory.check_layout().unwrap();
+}
+
+#[test]
+fn test_slab_rejects_oversized_objects() {
+    assert!("S;64;32".parse::<Cmd>().is_err());
+    assert!("S;0;32".parse::<Cmd>().is_err());
+    assert!("1000\nS;64;32\nA;1;64\n".parse::<CmdVec>().is_err());
+
+    // A cache built without the parser still never hands out an empty page.
+    let mut memory = Memory::new(1000);
+    exec(&mut memory, &[Cmd::Slab(64, 32), Cmd::Alloc(1, 64, None)]);
+    assert_eq!(memory.errors()[0].to_string(), "A;2;1000");
+    assert!(memory.slabs[0].slabs.is_empty());
+}
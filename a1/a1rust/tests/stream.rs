@@ -0,0 +1,66 @@
+use a1rust::simulator::{self, Options};
+
+fn stream(input: &str, options: &Options) -> String {
+    let mut out = vec![];
+    simulator::stream(input.as_bytes(), &mut out, options).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+// Streaming ends with the same output a file run produces, after the errors it reported on the way.
+#[test]
+fn test_stream_matches_file_run() {
+    let data = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/test.in")).unwrap();
+    let data: String = data
+        .lines()
+        .filter(|l| *l != "O")
+        .map(|l| format!("{}\n", l))
+        .collect();
+    let options = Options::default();
+    let out = stream(&data, &options);
+    let expected = simulator::run(&data, &options, "unused");
+    assert!(out.ends_with(&expected));
+
+    let errors: Vec<&str> = out[..out.len() - expected.len()].lines().collect();
+    for name in ["FirstFit", "BestFit", "WorstFit"] {
+        let streamed = errors
+            .iter()
+            .filter(|e| e.starts_with(&format!("{};", name)))
+            .count();
+        let section = expected
+            .split("\n\n")
+            .find(|s| s.starts_with(name))
+            .unwrap();
+        let reported = section
+            .split("Errors:\n")
+            .nth(1)
+            .unwrap()
+            .lines()
+            .filter(|l| *l != "None" && !l.starts_with("Leak"))
+            .count();
+        assert_eq!(streamed, reported);
+    }
+}
+
+#[test]
+fn test_stream_errors_and_checkpoints() {
+    let out = stream("100\nA;1;50\nA;2;60\nO\nD;1\n", &Options::default());
+    let mut lines = out.lines();
+    assert_eq!(lines.next(), Some("FirstFit;A;2;50"));
+    assert_eq!(lines.next(), Some("BestFit;A;2;50"));
+    assert_eq!(lines.next(), Some("WorstFit;A;2;50"));
+    // The checkpoint shows block 1 still allocated, the final output no longer does.
+    assert_eq!(lines.next(), Some("FirstFit"));
+    assert_eq!(out.matches("Allocated blocks:\n1;0;49\n").count(), 3);
+    assert_eq!(out.matches("FirstFit\n").count(), 2);
+}
+
+#[test]
+fn test_stream_size_flag() {
+    let options = Options::from_args(&["--size=100".to_owned()]);
+    let out = stream("A;1;50\n", &options);
+    assert!(out.starts_with("FirstFit\nSize:\n100\n"));
+
+    let mut sink = vec![];
+    assert!(simulator::stream("A;1;50\nM;200;50\n".as_bytes(), &mut sink, &options).is_err());
+    assert!(simulator::stream("".as_bytes(), &mut sink, &Options::default()).is_err());
+}
@@ -0,0 +1,28 @@
+use a1rust::{algos, memory::Memory, workload};
+
+#[test]
+fn test_duplicate_job_id() {
+    let err = "100\n4;30;0;5\n4;30;2;5\n"
+        .parse::<workload::Workload>()
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_queue_waits_for_room() {
+    let workload: workload::Workload = "100\n1;60;0;5\n2;60;2;5\n".parse().unwrap();
+    let mut memory = Memory::new(workload.size);
+    let stats = workload::simulate(
+        &workload,
+        &mut memory,
+        ("FirstFit", algos::first_fit),
+        true,
+        "",
+    );
+    assert_eq!(
+        (stats.completed, stats.rejected, stats.total_wait),
+        (2, 0, 3)
+    );
+    assert_eq!(stats.end_time, 10);
+    assert!(memory.live_blocks().is_empty());
+}
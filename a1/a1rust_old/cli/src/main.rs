@@ -1,8 +1,134 @@
-use core::simulation::{RunOption, Simulator};
+use std::{io::Write, path::PathBuf};
 
-use clap::{Arg, ArgAction, Command};
+use core::{
+    experiment::{Config, ConfigError, ExperimentConfig, Overrides},
+    method::Registry,
+    output::{self, Format},
+    simulation::{self, Compaction},
+};
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use tracing_subscriber::filter::LevelFilter;
+
+// Nothing but warnings by default, every `-v` shows one level more and `-q` silences everything.
+fn init_logging(quiet: bool, verbose: u8, format: &str) {
+    let level = match (quiet, verbose) {
+        (true, _) => LevelFilter::OFF,
+        (false, 0) => LevelFilter::WARN,
+        (false, 1) => LevelFilter::INFO,
+        (false, 2) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    };
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr);
+    match format {
+        "json" => subscriber.json().init(),
+        _ => subscriber.init(),
+    }
+}
+
+// Only what was given on the command line or in the environment ends up in the overrides,
+// everything else is left to the config.
+fn overrides(matches: &ArgMatches, registry: &Registry) -> Result<Overrides, ConfigError> {
+    let mut names = matches
+        .get_many::<String>("method")
+        .unwrap_or_default()
+        .map(|v| v.as_str())
+        .collect::<Vec<_>>();
+    for flag in ["first", "best", "worst"] {
+        if matches.get_flag(flag) || matches.get_flag("all") {
+            names.push(flag);
+        }
+    }
+    let methods = if names.is_empty() {
+        None
+    } else {
+        Some(registry.select(names)?)
+    };
+
+    Ok(Overrides {
+        inputs: matches
+            .get_many::<String>("files")
+            .map(|files| files.map(PathBuf::from).collect()),
+        methods,
+        memory: matches
+            .get_many::<u64>("memory")
+            .map(|sizes| sizes.copied().collect()),
+        compaction: matches
+            .get_one::<String>("compaction")
+            .map(|c| c.parse::<Compaction>().unwrap()),
+        dir: matches.get_one::<PathBuf>("out-dir").cloned(),
+        formats: matches
+            .get_many::<String>("format")
+            .map(|formats| formats.map(|f| f.parse::<Format>().unwrap()).collect()),
+        per_method: matches.get_flag("per-method").then_some(true),
+    })
+}
+
+// Reads commands from stdin as they arrive and writes to stdout, for traces piped in from
+// other tools. Rejected commands are written right away, the reports follow at the end.
+fn run_stdin(overrides: Overrides, registry: &Registry) -> Result<(), ConfigError> {
+    let methods = overrides
+        .methods
+        .unwrap_or_else(|| registry.select(["first"]).unwrap());
+    let size = match overrides.memory.as_deref() {
+        None | Some([]) => None,
+        Some([size]) => Some(*size),
+        Some(_) => return Err(ConfigError::Stdin("a single memory size".to_owned())),
+    };
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout().lock();
+    let results = simulation::stream(
+        stdin.lock(),
+        &mut stdout,
+        &methods,
+        size,
+        overrides.compaction.unwrap_or_default(),
+    )
+    .map_err(|err| ConfigError::Input("stdin".to_owned(), err))?;
+    for format in overrides.formats.unwrap_or(vec![Format::Text]) {
+        let out = output::render(format, &results)?;
+        stdout
+            .write_all(out.as_bytes())
+            .map_err(|err| ConfigError::Io("stdout".to_owned(), err))?;
+    }
+    Ok(())
+}
+
+fn run(matches: &ArgMatches) -> Result<(), ConfigError> {
+    let registry = Registry::default();
+    let overrides = overrides(matches, &registry)?;
+    if matches.get_flag("stdin") {
+        return run_stdin(overrides, &registry);
+    }
+    let only = matches
+        .get_many::<String>("experiment")
+        .unwrap_or_default()
+        .map(|v| v.as_str())
+        .collect::<Vec<_>>();
+
+    // Without a config the flags describe a single experiment, which runs first fit unless
+    // told otherwise.
+    let config = match matches.get_one::<PathBuf>("config") {
+        Some(path) => Config::from_file(path)?,
+        None => Config {
+            experiments: vec![ExperimentConfig {
+                name: "default".to_owned(),
+                methods: Some(vec!["first".to_owned()]),
+                ..ExperimentConfig::default()
+            }],
+            ..Config::default()
+        },
+    };
+    for experiment in config.experiments(&registry, &overrides, &only)? {
+        experiment.run()?;
+    }
+    Ok(())
+}
 
 fn main() {
+    dotenv::dotenv().ok();
     let matches = Command::new("run")
         .arg(
             Arg::new("all")
@@ -28,6 +154,95 @@ fn main() {
                 .long("worst")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("method")
+                .short('m')
+                .long("method")
+                .env("SIMULATE_METHODS")
+                .value_delimiter(',')
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .conflicts_with("verbose")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .action(ArgAction::Count),
+        )
+        .arg(
+            Arg::new("log-format")
+                .long("log-format")
+                .value_parser(["text", "json"])
+                .default_value("text"),
+        )
+        .arg(
+            Arg::new("out-dir")
+                .short('o')
+                .long("out-dir")
+                .env("SIMULATE_OUT_DIR")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .env("SIMULATE_FORMAT")
+                .value_delimiter(',')
+                .value_parser(["text", "json", "debug"])
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("per-method")
+                .long("per-method")
+                .env("SIMULATE_PER_METHOD")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("config")
+                .short('c')
+                .long("config")
+                .env("SIMULATE_CONFIG")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("experiment")
+                .short('e')
+                .long("experiment")
+                .value_delimiter(',')
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("memory")
+                .long("memory")
+                .env("SIMULATE_MEMORY")
+                .value_delimiter(',')
+                .value_parser(clap::value_parser!(u64))
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("jobs")
+                .short('j')
+                .long("jobs")
+                .env("SIMULATE_JOBS")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("compaction")
+                .long("compaction")
+                .env("SIMULATE_COMPACTION")
+                .value_parser(["never", "on-failure", "after-dealloc"]),
+        )
+        .arg(
+            Arg::new("stdin")
+                .long("stdin")
+                .conflicts_with_all(["files", "config"])
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("files")
                 .short('F')
@@ -36,17 +251,22 @@ fn main() {
         )
         .get_matches();
 
-    let all = matches.get_flag("all");
-    let first = matches.get_flag("first");
-    let best = matches.get_flag("best");
-    let worst = matches.get_flag("worst");
+    init_logging(
+        matches.get_flag("quiet"),
+        matches.get_count("verbose"),
+        matches.get_one::<String>("log-format").unwrap(),
+    );
 
-    let files = matches
-        .get_many::<String>("files")
-        .unwrap_or_default()
-        .map(|v| v.as_str())
-        .collect::<Vec<_>>();
+    // Runs use one thread per core unless told otherwise.
+    if let Some(jobs) = matches.get_one::<usize>("jobs") {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(*jobs)
+            .build_global()
+            .unwrap();
+    }
 
-    let mut simulation = Simulator::new(files, RunOption::from((all, first, best, worst)));
-    simulation.run()
+    if let Err(err) = run(&matches) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
 }
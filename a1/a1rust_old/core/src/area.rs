@@ -1,4 +1,8 @@
-use crate::block::{Block, BlockId};
+use crate::{
+    bitmap::Bitmap,
+    block::{Block, BlockId},
+    select,
+};
 use thiserror::Error;
 
 pub type AResult<T> = std::result::Result<T, AError>;
@@ -8,12 +12,27 @@ pub enum AError {
     #[error("Unable to allocate {0} bytes for block {1}. Available amount is {2} bytes.")]
     Alloc(u64, u64, u64),
 
+    #[error("Unable to allocate block {0}. Block size must be positive.")]
+    Empty(u64),
+
     #[error("Unable to deallocate block {0}. Block does not exist.")]
     Dealloc(BlockId),
 
     #[error("Unable to compact. No free blocks available.")]
     Compact,
 
+    #[error("Block {0} at ({1}, {2}) has an end address that does not match its size.")]
+    Size(BlockId, u64, u64),
+
+    #[error("Block {0} at ({1}, {2}) overlaps the block before it.")]
+    Overlap(BlockId, u64, u64),
+
+    #[error("Addresses {0} to {1} are not covered by any block.")]
+    Gap(u64, u64),
+
+    #[error("Block {0} at ({1}, {2}) lies outside of the area of size {3}.")]
+    OutOfBounds(BlockId, u64, u64, u64),
+
     #[error(transparent)]
     BlockError(#[from] crate::block::BError),
 }
@@ -23,6 +42,11 @@ pub struct Area {
     pub free_blocks: Vec<Block>,
     pub used_blocks: Vec<Block>,
     pub size: u64,
+    /// Where next fit resumes its search, right after the last allocated block.
+    pub cursor: u64,
+    /// Only set once the bitmap method has allocated in the area, which from then on keeps
+    /// the map and the block lists in step.
+    pub bitmap: Option<Bitmap>,
 }
 
 impl Area {
@@ -31,68 +55,77 @@ impl Area {
             size,
             free_blocks: vec![Block::new_free(0, size)],
             used_blocks: vec![],
+            cursor: 0,
+            bitmap: None,
         }
     }
 
-    fn alloc<F: Fn(Vec<Block>) -> Option<Block>>(
+    /// Allocates with any selection policy. The policy only ever sees the free list
+    /// borrowed and answers with an index into it, so nothing is copied per allocation.
+    pub fn alloc_with<F: Fn(&[Block], u64) -> Option<usize>>(
         &mut self,
         block_id: u64,
         size: u64,
-        fun: F,
+        select: F,
     ) -> AResult<()> {
-        let free_block = fun(self.free_blocks.clone());
-        match free_block {
-            Some(block) => {
-                let new_blocks = block.take(block_id, size);
-                match new_blocks {
-                    Ok(blocks) => {
-                        self.take_from_free(&block, blocks);
-                        Ok(())
-                    }
-                    Err(e) => Err(e.into()),
-                }
+        if size == 0 {
+            return Err(AError::Empty(block_id));
+        }
+        match select(&self.free_blocks, size) {
+            Some(idx) => {
+                let new_blocks = self.free_blocks[idx].take(block_id, size)?;
+                self.take_from_free(idx, new_blocks);
+                Ok(())
             }
-            None => Err(AError::Alloc(
-                size,
-                block_id,
-                self.free_blocks.iter().map(|b| b.size).sum(),
-            )),
+            None => Err(AError::Alloc(size, block_id, self.calc_free_memory())),
         }
     }
 
     pub fn alloc_first_fit(&mut self, block_id: u64, size: u64) -> AResult<()> {
-        self.alloc(block_id, size, |blocks| {
-            blocks.iter().find(|b| b.size >= size).cloned()
-        })
+        self.alloc_with(block_id, size, select::first_fit)
     }
 
     pub fn alloc_best_fit(&mut self, block_id: u64, size: u64) -> AResult<()> {
-        self.alloc(block_id, size, |blocks| {
-            let mut out: Option<Block> = None;
-            for block in blocks {
-                if block.size >= size {
-                    match out {
-                        Some(ref mut b) => {
-                            if block.size < b.size {
-                                *b = block.clone();
-                            }
-                        }
-                        None => {
-                            out = Some(block.clone());
-                        }
-                    }
-                }
-            }
-            out
-        })
+        self.alloc_with(block_id, size, select::best_fit)
     }
 
     pub fn alloc_worst_fit(&mut self, block_id: u64, size: u64) -> AResult<()> {
-        self.alloc(block_id, size, |blocks| {
-            blocks.iter().max_by_key(|b| b.size).cloned()
+        self.alloc_with(block_id, size, select::worst_fit)
+    }
+
+    pub fn alloc_next_fit(&mut self, block_id: u64, size: u64) -> AResult<()> {
+        let cursor = self.cursor;
+        self.alloc_with(block_id, size, |blocks: &[Block], size| {
+            select::next_fit(blocks, size, cursor)
         })
     }
 
+    /// Rounds `size` up to whole units and takes the first run of free units that is long
+    /// enough. The used block covers the rounded size.
+    pub fn alloc_bitmap(&mut self, block_id: u64, size: u64) -> AResult<()> {
+        if size == 0 {
+            return Err(AError::Empty(block_id));
+        }
+        let bitmap = match &mut self.bitmap {
+            Some(bitmap) => bitmap,
+            None => self
+                .bitmap
+                .insert(Bitmap::from_area(self, Bitmap::DEFAULT_UNIT)),
+        };
+        let units = bitmap.units_for(size);
+        let Some(start) = bitmap.find(units) else {
+            return Err(AError::Alloc(
+                size,
+                block_id,
+                bitmap.free_units() * bitmap.unit,
+            ));
+        };
+        bitmap.take(block_id, start, units, size);
+        let unit = bitmap.unit;
+        self.take_range(block_id, start * unit, units * unit);
+        Ok(())
+    }
+
     pub fn dealloc(&mut self, block_id: u64) -> AResult<()> {
         let mut used_block_idx = None;
         for (i, block) in self.used_blocks.iter().enumerate() {
@@ -105,62 +138,89 @@ impl Area {
         match used_block_idx {
             Some(i) => {
                 let block = self.used_blocks.remove(i);
+                if let Some(bitmap) = &mut self.bitmap {
+                    bitmap.free(&block);
+                }
                 let mergable_block = self.free_blocks.iter_mut().find(|b| b.can_merge(&block));
                 match mergable_block {
                     Some(b) => match b.merge_replace(block) {
                         Ok(_) => Ok(()),
                         Err(e) => Err(e.into()),
                     },
-                    None => Ok(self.free_blocks.push(block.as_free())),
+                    None => {
+                        self.free_blocks.push(block.as_free());
+                        Ok(())
+                    }
                 }
             }
             None => Err(AError::Dealloc(BlockId::Used(block_id))),
         }
     }
 
+    /// Slides every used block down to the lowest address it can take, keeping their order,
+    /// and leaves all free memory as a single hole at the end of the area.
     pub fn compact(&mut self) -> AResult<()> {
         if self.free_blocks.is_empty() {
             return Err(AError::Compact);
         }
 
-        self.free_blocks.sort_by(|a, b| a.cmp_start_addr(&b));
-        self.used_blocks.sort_by(|a, b| a.cmp_start_addr(&b));
+        self.used_blocks.sort_by(|a, b| a.cmp_start_addr(b));
+        let mut next_addr = 0;
+        for block in self.used_blocks.iter_mut() {
+            block.relocate(next_addr);
+            next_addr += block.size;
+        }
 
-        let first_free_block = self.free_blocks.get(0).unwrap();
-        let mut offset = 0;
-        let mut new_used_blocks = vec![];
+        self.free_blocks = vec![Block::new_free(next_addr, self.size - next_addr)];
+        self.cursor = next_addr;
+        if let Some(mut bitmap) = self.bitmap.take() {
+            bitmap.rebuild(self);
+            self.bitmap = Some(bitmap);
+        }
+        self.check_layout()
+    }
 
-        let _itr = self.used_blocks.iter();
-        let _bs = _itr
-            .map(|b| {
-                offset += b.size;
-                match b.start_addr >= first_free_block.start_addr {
-                    true => Block::new(b.id.clone(), first_free_block.start_addr + offset, b.size),
-                    false => Block::new(b.id.clone(), b.start_addr, b.size),
-                }
-            })
-            .collect::<Vec<Block>>();
+    /// Checks that the blocks tile the area exactly: every address is covered by exactly one block.
+    pub fn check_layout(&self) -> AResult<()> {
+        let mut blocks: Vec<&Block> = self
+            .used_blocks
+            .iter()
+            .chain(self.free_blocks.iter())
+            .collect();
+        blocks.sort_by(|a, b| a.cmp_start_addr(b));
 
-        for block in self.used_blocks.iter() {
-            match block.start_addr > first_free_block.start_addr {
-                true => new_used_blocks.push(Block::new(
+        let mut next_addr = 0;
+        for block in blocks {
+            if block.is_empty() || block.end_addr != block.start_addr + block.size - 1 {
+                return Err(AError::Size(
                     block.id.clone(),
-                    first_free_block.start_addr + offset,
-                    block.size,
-                )),
-                false => {
-                    new_used_blocks.push(Block::new(block.id.clone(), block.start_addr, block.size))
-                }
+                    block.start_addr,
+                    block.end_addr,
+                ));
+            }
+            if block.start_addr < next_addr {
+                return Err(AError::Overlap(
+                    block.id.clone(),
+                    block.start_addr,
+                    block.end_addr,
+                ));
+            }
+            if block.start_addr > next_addr {
+                return Err(AError::Gap(next_addr, block.start_addr - 1));
+            }
+            if block.end_addr >= self.size {
+                return Err(AError::OutOfBounds(
+                    block.id.clone(),
+                    block.start_addr,
+                    block.end_addr,
+                    self.size,
+                ));
             }
-            offset += block.size;
+            next_addr = block.end_addr + 1;
+        }
+        if next_addr < self.size {
+            return Err(AError::Gap(next_addr, self.size - 1));
         }
-
-        let total_free_memory = self.calc_free_memory();
-        let total_free_memory_block =
-            Block::new_free(self.size - total_free_memory, total_free_memory);
-
-        self.used_blocks = new_used_blocks;
-        self.free_blocks = vec![total_free_memory_block];
         Ok(())
     }
 
@@ -168,20 +228,39 @@ impl Area {
         self.free_blocks.iter().map(|b| b.size).sum()
     }
 
-    fn take_from_free(&mut self, free_block: &Block, new_blocks: (Block, Block)) {
-        let idx = self
-            .free_blocks
-            .iter()
-            .position(|b| b == free_block)
-            .unwrap();
+    fn take_from_free(&mut self, idx: usize, new_blocks: (Block, Block)) {
         self.free_blocks.remove(idx);
 
+        self.cursor = (new_blocks.0.end_addr + 1) % self.size;
         self.used_blocks.push(new_blocks.0);
         if new_blocks.1.size > 0 {
             self.free_blocks.push(new_blocks.1);
         }
     }
 
+    // Cuts `size` bytes from `start` out of the free blocks, which may span more than one
+    // of them, since freed neighbours are not always merged.
+    fn take_range(&mut self, block_id: u64, start: u64, size: u64) {
+        let end = start + size - 1;
+        let mut free_blocks = Vec::with_capacity(self.free_blocks.len() + 1);
+        for block in self.free_blocks.drain(..) {
+            if block.end_addr < start || block.start_addr > end {
+                free_blocks.push(block);
+                continue;
+            }
+            if block.start_addr < start {
+                free_blocks.push(Block::new_free(block.start_addr, start - block.start_addr));
+            }
+            if block.end_addr > end {
+                free_blocks.push(Block::new_free(end + 1, block.end_addr - end));
+            }
+        }
+        self.free_blocks = free_blocks;
+        self.cursor = (end + 1) % self.size;
+        self.used_blocks
+            .push(Block::new_used(block_id, start, size));
+    }
+
     pub fn as_byte_array(&self) -> Vec<u8> {
         let mut bytes = vec![0; self.size as usize];
         let mut blocks: Vec<&Block> = self
@@ -189,7 +268,7 @@ impl Area {
             .iter()
             .chain(self.free_blocks.iter())
             .collect();
-        blocks.sort_by(|a, b| a.cmp_start_addr(&b));
+        blocks.sort_by(|a, b| a.cmp_start_addr(b));
         for block in blocks {
             for i in block.start_addr..=block.end_addr {
                 if let BlockId::Used(_) = block.id {
@@ -201,13 +280,13 @@ impl Area {
     }
 
     pub fn fragmentation(&self) -> f64 {
-        let largest_block = self.free_blocks.iter().max_by_key(|b| b.size).unwrap().size;
         let free_memory = self.calc_free_memory();
+        if free_memory == 0 {
+            return 0f64;
+        }
+        let largest_block = self.free_blocks.iter().map(|b| b.size).max().unwrap_or(0);
         let fragmentation = 1f64 - (largest_block as f64 / free_memory as f64);
-        println!(
-            "largest block: {}, free memory: {}, fragmentation: {}",
-            largest_block, free_memory, fragmentation
-        );
+        tracing::trace!(largest_block, free_memory, fragmentation, "fragmentation");
         fragmentation
     }
 }
@@ -219,7 +298,7 @@ mod mem_tests {
         let mut area = super::Area::new(100);
         area.alloc_first_fit(1, 10).unwrap();
         area.alloc_first_fit(2, 80).unwrap();
-        assert_eq!(area.alloc_first_fit(3, 20).is_err(), true);
+        assert!(area.alloc_first_fit(3, 20).is_err());
     }
 
     #[test]
@@ -227,7 +306,7 @@ mod mem_tests {
         let mut area = super::Area::new(100);
         area.alloc_first_fit(1, 10).unwrap();
         area.alloc_first_fit(2, 80).unwrap();
-        assert_eq!(area.alloc_first_fit(3, 10).is_ok(), true);
+        assert!(area.alloc_first_fit(3, 10).is_ok());
     }
 
     #[test]
@@ -289,6 +368,108 @@ mod mem_tests {
         assert_eq!(area.free_blocks[0].size, 30);
         assert_eq!(area.used_blocks[0].size, 30);
         assert_eq!(area.used_blocks[1].size, 40);
+        assert_eq!(area.used_blocks[0].start_addr, 0);
+        assert_eq!(area.used_blocks[1].start_addr, 30);
+        assert_eq!(area.free_blocks[0].start_addr, 70);
+        assert_eq!(area.free_blocks[0].end_addr, 99);
+    }
+
+    // Compacts and checks that the used blocks are packed from address 0 in their
+    // previous order, followed by a single hole up to the end of the area.
+    #[cfg(test)]
+    fn assert_compacted(area: &mut super::Area) {
+        let mut before = area.used_blocks.clone();
+        before.sort_by(|a, b| a.cmp_start_addr(b));
+        area.compact().unwrap();
+
+        let mut next_addr = 0;
+        for (old, new) in before.iter().zip(area.used_blocks.iter()) {
+            assert_eq!(new.id, old.id);
+            assert_eq!(new.size, old.size);
+            assert_eq!(new.start_addr, next_addr);
+            assert_eq!(new.end_addr, next_addr + new.size - 1);
+            next_addr += new.size;
+        }
+        assert_eq!(area.used_blocks.len(), before.len());
+        assert_eq!(area.free_blocks.len(), 1);
+        assert_eq!(area.free_blocks[0].start_addr, next_addr);
+        assert_eq!(area.free_blocks[0].end_addr, area.size - 1);
+        area.check_layout().unwrap();
+    }
+
+    #[test]
+    fn test_compact_hole_at_start() {
+        let mut area = super::Area::new(100);
+        area.alloc_first_fit(1, 20).unwrap();
+        area.alloc_first_fit(2, 30).unwrap();
+        area.alloc_first_fit(3, 50).unwrap();
+        area.dealloc(1).unwrap();
+
+        assert_compacted(&mut area);
+        assert_eq!(area.used_blocks[0].start_addr, 0);
+        assert_eq!(area.used_blocks[1].start_addr, 30);
+        assert_eq!(area.free_blocks[0].start_addr, 80);
+    }
+
+    #[test]
+    fn test_compact_hole_in_middle() {
+        let mut area = super::Area::new(100);
+        area.alloc_first_fit(1, 20).unwrap();
+        area.alloc_first_fit(2, 30).unwrap();
+        area.alloc_first_fit(3, 10).unwrap();
+        area.alloc_first_fit(4, 40).unwrap();
+        area.dealloc(2).unwrap();
+
+        assert_compacted(&mut area);
+        assert_eq!(area.used_blocks[1].start_addr, 20);
+        assert_eq!(area.used_blocks[2].start_addr, 30);
+        assert_eq!(area.free_blocks[0].start_addr, 70);
+    }
+
+    #[test]
+    fn test_compact_hole_at_end() {
+        let mut area = super::Area::new(100);
+        area.alloc_first_fit(1, 20).unwrap();
+        area.alloc_first_fit(2, 30).unwrap();
+        area.alloc_first_fit(3, 50).unwrap();
+        area.dealloc(3).unwrap();
+
+        assert_compacted(&mut area);
+        assert_eq!(area.used_blocks[1].start_addr, 20);
+        assert_eq!(area.free_blocks[0].start_addr, 50);
+        assert_eq!(area.free_blocks[0].size, 50);
+    }
+
+    #[test]
+    fn test_compact_keeps_order() {
+        let mut area = super::Area::new(100);
+        for id in 0..10 {
+            area.alloc_first_fit(id, 10).unwrap();
+        }
+        for id in [0, 3, 4, 9] {
+            area.dealloc(id).unwrap();
+        }
+        area.alloc_first_fit(10, 5).unwrap();
+
+        assert_compacted(&mut area);
+        let ids: Vec<String> = area.used_blocks.iter().map(|b| b.id.to_string()).collect();
+        assert_eq!(ids, ["10", "1", "2", "5", "6", "7", "8"]);
+        assert_eq!(area.free_blocks[0].size, 35);
+    }
+
+    #[test]
+    fn test_check_layout() {
+        let mut area = super::Area::new(100);
+        area.alloc_first_fit(1, 20).unwrap();
+        area.check_layout().unwrap();
+        area.used_blocks[0].relocate(10);
+        assert!(matches!(area.check_layout(), Err(super::AError::Gap(0, 9))));
+        area.used_blocks[0].relocate(0);
+        area.free_blocks[0].relocate(10);
+        assert!(matches!(
+            area.check_layout(),
+            Err(super::AError::Overlap(_, 10, 89))
+        ));
     }
 
     #[test]
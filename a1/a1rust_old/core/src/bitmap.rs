@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    area::Area,
+    block::{Block, BlockId},
+};
+
+const WORD: u64 = u64::BITS as u64;
+
+/// An occupancy map with one bit per `unit` bytes, set while the unit is in use. The bits
+/// past the last unit are set as well, so a full word can be skipped without a bounds check.
+#[derive(Debug, Clone)]
+pub struct Bitmap {
+    pub unit: u64,
+    /// Bytes at the end of the area that do not fill a whole unit are never handed out.
+    pub units: u64,
+    words: Vec<u64>,
+    /// What every live block asked for, before it was rounded up to whole units.
+    requested: BTreeMap<u64, u64>,
+}
+
+impl Bitmap {
+    pub const DEFAULT_UNIT: u64 = 8;
+
+    /// Maps `area` the way `Area::as_byte_array` sees it, a unit being in use as soon as
+    /// one of its bytes is.
+    pub fn from_area(area: &Area, unit: u64) -> Self {
+        let mut bitmap = Self {
+            unit,
+            units: area.size / unit,
+            words: vec![],
+            requested: BTreeMap::new(),
+        };
+        bitmap.rebuild(area);
+        bitmap
+    }
+
+    /// Maps the area anew, e.g. after compaction moved its blocks.
+    pub fn rebuild(&mut self, area: &Area) {
+        self.words = vec![0; self.units.div_ceil(WORD) as usize];
+        self.fill(
+            self.units,
+            self.words.len() as u64 * WORD - self.units,
+            true,
+        );
+        let bytes = area.as_byte_array();
+        for (i, unit) in bytes.chunks(self.unit as usize).enumerate() {
+            if (i as u64) < self.units && unit.contains(&1) {
+                self.fill(i as u64, 1, true);
+            }
+        }
+    }
+
+    pub fn units_for(&self, size: u64) -> u64 {
+        size.div_ceil(self.unit)
+    }
+
+    /// The first run of `units` clear bits. Full words are skipped and empty words taken
+    /// whole, only words that are partly in use are looked at bit by bit.
+    pub fn find(&self, units: u64) -> Option<u64> {
+        let mut start = 0;
+        let mut run = 0;
+        for (i, word) in self.words.iter().enumerate() {
+            let base = i as u64 * WORD;
+            match *word {
+                u64::MAX => run = 0,
+                0 => {
+                    if run == 0 {
+                        start = base;
+                    }
+                    run += WORD;
+                }
+                word => {
+                    for bit in 0..WORD {
+                        if word >> bit & 1 == 1 {
+                            run = 0;
+                            continue;
+                        }
+                        if run == 0 {
+                            start = base + bit;
+                        }
+                        run += 1;
+                        if run >= units {
+                            return Some(start);
+                        }
+                    }
+                }
+            }
+            if run >= units {
+                return Some(start);
+            }
+        }
+        None
+    }
+
+    /// Marks `units` units from `start` as taken by `block_id`, which asked for `size` bytes.
+    pub fn take(&mut self, block_id: u64, start: u64, units: u64, size: u64) {
+        self.fill(start, units, true);
+        self.requested.insert(block_id, size);
+    }
+
+    /// Clears the run of units `block` covers.
+    pub fn free(&mut self, block: &Block) {
+        self.fill(block.start_addr / self.unit, block.size / self.unit, false);
+        if let BlockId::Used(id) = block.id {
+            self.requested.remove(&id);
+        }
+    }
+
+    // Sets or clears a run a word at a time, masking only the words at either end.
+    fn fill(&mut self, start: u64, units: u64, used: bool) {
+        let end = start + units;
+        let mut pos = start;
+        while pos < end {
+            let word = (pos / WORD) as usize;
+            let offset = pos % WORD;
+            let len = (WORD - offset).min(end - pos);
+            let mask = if len == WORD {
+                u64::MAX
+            } else {
+                ((1 << len) - 1) << offset
+            };
+            if used {
+                self.words[word] |= mask;
+            } else {
+                self.words[word] &= !mask;
+            }
+            pos += len;
+        }
+    }
+
+    pub fn free_units(&self) -> u64 {
+        self.words.iter().map(|w| w.count_zeros() as u64).sum()
+    }
+
+    /// The bytes the map itself takes, one bit per unit.
+    pub fn overhead(&self) -> u64 {
+        self.units.div_ceil(8)
+    }
+
+    /// The bytes the used blocks hold on top of what they asked for, from rounding to units.
+    pub fn rounding(&self, used_blocks: &[Block]) -> u64 {
+        used_blocks
+            .iter()
+            .map(|block| match block.id {
+                BlockId::Used(id) => self.requested.get(&id).map_or(0, |size| block.size - size),
+                BlockId::Free => 0,
+            })
+            .sum()
+    }
+}
+
+mod bitmap_tests {
+    #[cfg(test)]
+    use crate::area::Area;
+
+    #[test]
+    fn test_find_across_words() {
+        let area = Area::new(8 * 200);
+        let mut bitmap = super::Bitmap::from_area(&area, 8);
+        assert_eq!(bitmap.units, 200);
+        assert_eq!(bitmap.free_units(), 200);
+        bitmap.take(0, 0, 60, 480);
+        bitmap.take(1, 70, 60, 480);
+        // The 10 units between the blocks are too few, the run has to start after block 1.
+        assert_eq!(bitmap.find(10), Some(60));
+        assert_eq!(bitmap.find(11), Some(130));
+        assert_eq!(bitmap.find(70), Some(130));
+        assert_eq!(bitmap.find(71), None);
+    }
+
+    #[test]
+    fn test_free_clears_run() {
+        let mut area = Area::new(1000);
+        area.alloc_bitmap(0, 100).unwrap();
+        area.alloc_bitmap(1, 600).unwrap();
+        area.dealloc(0).unwrap();
+        let bitmap = area.bitmap.as_ref().unwrap();
+        assert_eq!(bitmap.find(13), Some(0));
+        assert_eq!(bitmap.find(14), Some(88));
+        assert_eq!(bitmap.free_units(), 125 - 75);
+    }
+
+    #[test]
+    fn test_from_area() {
+        let mut area = Area::new(100);
+        area.alloc_first_fit(0, 10).unwrap();
+        let bitmap = super::Bitmap::from_area(&area, 8);
+        // Bytes 96 to 99 do not fill a unit, and block 0 reaches into the second one.
+        assert_eq!(bitmap.units, 12);
+        assert_eq!(bitmap.find(1), Some(2));
+        assert_eq!(bitmap.overhead(), 2);
+    }
+
+    #[test]
+    fn test_compact_and_layout() {
+        let (mut area, cmds) = crate::generator::Generator {
+            seed: 3,
+            commands: 500,
+            memory: 4000,
+            max_size: 40,
+        }
+        .generate();
+        for cmd in cmds {
+            let _ = match cmd.size {
+                Some(size) => area.alloc_bitmap(cmd.block_id.unwrap(), size),
+                None => area.dealloc(cmd.block_id.unwrap()),
+            };
+            area.check_layout().unwrap();
+        }
+        area.compact().unwrap();
+        let bitmap = area.bitmap.as_ref().unwrap();
+        let used: u64 = area.used_blocks.iter().map(|b| b.size).sum();
+        assert_eq!(bitmap.free_units(), bitmap.units - used / 8);
+        assert_eq!(bitmap.find(bitmap.free_units()), Some(used / 8));
+        assert!(bitmap.rounding(&area.used_blocks) < 8 * area.used_blocks.len() as u64);
+    }
+}
@@ -1,5 +1,3 @@
-use std::ops::Add;
-
 use thiserror::Error;
 
 pub type BResult<T> = std::result::Result<T, BError>;
@@ -79,18 +77,18 @@ pub enum BlockId {
 
 impl PartialOrd for BlockId {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        match (self, other) {
-            (BlockId::Free, BlockId::Free) => Some(std::cmp::Ordering::Equal),
-            (BlockId::Free, BlockId::Used(_)) => Some(std::cmp::Ordering::Less),
-            (BlockId::Used(_), BlockId::Free) => Some(std::cmp::Ordering::Greater),
-            (BlockId::Used(a), BlockId::Used(b)) => a.partial_cmp(b),
-        }
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for BlockId {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap()
+        match (self, other) {
+            (BlockId::Free, BlockId::Free) => std::cmp::Ordering::Equal,
+            (BlockId::Free, BlockId::Used(_)) => std::cmp::Ordering::Less,
+            (BlockId::Used(_), BlockId::Free) => std::cmp::Ordering::Greater,
+            (BlockId::Used(a), BlockId::Used(b)) => a.cmp(b),
+        }
     }
 }
 
@@ -259,8 +257,8 @@ mod block_tests {
         let b2 = super::Block::new_free(100, 100);
         let b3 = super::Block::new_free(250, 100);
 
-        assert_eq!(b1.can_merge(&b2), true);
-        assert_eq!(b1.can_merge(&b3), false);
+        assert!(b1.can_merge(&b2));
+        assert!(!b1.can_merge(&b3));
     }
 
     #[test]
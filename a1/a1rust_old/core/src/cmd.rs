@@ -4,18 +4,38 @@ pub type ParseResult<T> = std::result::Result<T, ParseError>;
 
 #[derive(Debug, Error)]
 pub enum ParseError {
-    #[error("Unable to parse {0} as a command. [ Options are A (Allocate), D (Deallocate) and C (Compact) ].")]
+    #[error("Unable to parse {0} as a command. [ Options are A (Allocate), D (Deallocate), C (Compact) and O (Output) ].")]
     InvalidCommand(String),
 
+    #[error("Command {0} only matters to the a1rust simulator and was skipped.")]
+    Ignored(String),
+
+    #[error("Command {0} changes the memory in a way only the a1rust simulator models.")]
+    Unsupported(String),
+
+    #[error("Unable to parse {0} as the memory size. Memory size must be a positive integer.")]
+    InvalidMemorySize(String),
+
+    #[error("The input has to start with the memory size.")]
+    MissingSize,
+
     #[error("Unable to parse {0} as a block id. Block id must be a positive integer.")]
     InvalidBlockId(String),
 
-    #[error("Unable to parse {0} as a block size. Block size must be a positive integer.")]
+    #[error("Unable to parse {0} as a block size. Block size must be a non-negative integer.")]
     InvalidBlockSize(String),
 
-    #[error("Command {0} requires a block id and a block size.")]
+    #[error("Unable to parse {0} as a process id. Process id must be a positive integer.")]
+    InvalidProcessId(String),
+
+    #[error(
+        "Command {0} is missing parameters. [ A needs a block id and a block size, D a block id ]."
+    )]
     MissingParameters(String),
 
+    #[error("Command {0}: {1}")]
+    At(usize, Box<ParseError>),
+
     #[error("Invalid Format: {0}")]
     Format(String),
 
@@ -31,17 +51,27 @@ pub enum CmdType {
     Alloc,
     Dealloc,
     Compact,
+    Output,
 }
 
+#[derive(Debug, Clone)]
 pub struct Cmd {
     pub ty: CmdType,
     pub block_id: Option<u64>,
     pub size: Option<u64>,
+    /// Owner of an allocation. Accepted for traces written for a1rust, this simulator
+    /// has no processes and ignores it.
+    pub pid: Option<u64>,
 }
 
 impl Cmd {
     pub fn new(ty: CmdType, block_id: Option<u64>, size: Option<u64>) -> Self {
-        Self { ty, block_id, size }
+        Self {
+            ty,
+            block_id,
+            size,
+            pid: None,
+        }
     }
 
     pub fn alloc(block_id: u64, size: u64) -> Self {
@@ -55,38 +85,22 @@ impl Cmd {
     pub fn compact() -> Self {
         Self::new(CmdType::Compact, None, None)
     }
+
+    pub fn output() -> Self {
+        Self::new(CmdType::Output, None, None)
+    }
 }
 
+// Commands of the a1rust format that this simulator has no model for. Those that leave the
+// blocks as they are can be skipped, the others would make the layouts differ.
+const IGNORED: [&str; 5] = ["W", "V", "T", "U", "L"];
+const UNSUPPORTED: [&str; 10] = ["M", "N", "S", "H", "R", "G", "+", "-", "K", "Q"];
+
 impl TryFrom<String> for Cmd {
     type Error = ParseError;
 
     fn try_from(s: String) -> ParseResult<Self> {
-        let mut iter = s.split(';');
-        let cmd = iter.next().unwrap();
-        match cmd {
-            "A" => {
-                let id = iter.next();
-                let size = iter.next();
-                match (id, size) {
-                    (Some(id), Some(size)) => {
-                        let id = id
-                            .parse()
-                            .map_err(|_| ParseError::InvalidBlockId(id.clone().to_owned()))?;
-                        let size = size
-                            .parse()
-                            .map_err(|_| ParseError::InvalidBlockSize(size.clone().to_owned()))?;
-                        Ok(Self::alloc(id, size))
-                    }
-                    _ => Err(ParseError::MissingParameters(cmd.clone().to_owned())),
-                }
-            }
-            "D" => {
-                let block_id = iter.next().unwrap().parse().unwrap();
-                Ok(Self::dealloc(block_id))
-            }
-            "C" => Ok(Self::compact()),
-            _ => Err(ParseError::InvalidCommand(cmd.clone().to_owned())),
-        }
+        Self::try_from(s.as_str())
     }
 }
 
@@ -94,6 +108,72 @@ impl TryFrom<&str> for Cmd {
     type Error = ParseError;
 
     fn try_from(s: &str) -> ParseResult<Self> {
-        Self::try_from(s.to_owned())
+        let mut params = s.split(';');
+        let cmd = params.next().unwrap_or_default();
+        let params: Vec<&str> = params.collect();
+        let id = |id: &str| {
+            id.parse()
+                .map_err(|_| ParseError::InvalidBlockId(id.to_owned()))
+        };
+        // An empty block is a valid command, it is the allocation that fails, as in a1rust.
+        let size = |size: &str| {
+            size.parse()
+                .map_err(|_| ParseError::InvalidBlockSize(size.to_owned()))
+        };
+        match (cmd, params.as_slice()) {
+            ("A", [block_id, block_size]) => Ok(Self::alloc(id(block_id)?, size(block_size)?)),
+            ("A", [block_id, block_size, pid]) => {
+                let mut cmd = Self::alloc(id(block_id)?, size(block_size)?);
+                cmd.pid = Some(
+                    pid.parse()
+                        .map_err(|_| ParseError::InvalidProcessId(pid.to_string()))?,
+                );
+                Ok(cmd)
+            }
+            ("D", [block_id]) => Ok(Self::dealloc(id(block_id)?)),
+            ("C", []) => Ok(Self::compact()),
+            ("O", []) => Ok(Self::output()),
+            ("A", [] | [_]) | ("D", []) => Err(ParseError::MissingParameters(s.to_owned())),
+            ("A" | "D" | "C" | "O", _) => Err(ParseError::Format(s.to_owned())),
+            (cmd, _) if IGNORED.contains(&cmd) => Err(ParseError::Ignored(s.to_owned())),
+            (cmd, _) if UNSUPPORTED.contains(&cmd) => Err(ParseError::Unsupported(s.to_owned())),
+            (cmd, _) => Err(ParseError::InvalidCommand(cmd.to_owned())),
+        }
+    }
+}
+
+mod cmd_tests {
+    #[cfg(test)]
+    use super::{Cmd, CmdType, ParseError};
+
+    #[test]
+    fn test_parse() {
+        let cmd = Cmd::try_from("A;1;100;2").unwrap();
+        assert_eq!(cmd.ty, CmdType::Alloc);
+        assert_eq!(
+            (cmd.block_id, cmd.size, cmd.pid),
+            (Some(1), Some(100), Some(2))
+        );
+        assert_eq!(Cmd::try_from("D;1").unwrap().block_id, Some(1));
+        assert_eq!(Cmd::try_from("C").unwrap().ty, CmdType::Compact);
+        assert_eq!(Cmd::try_from("O").unwrap().ty, CmdType::Output);
+        assert_eq!(Cmd::try_from("A;1;0").unwrap().size, Some(0));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = |s: &str| Cmd::try_from(s).unwrap_err();
+        assert!(matches!(err("D"), ParseError::MissingParameters(_)));
+        assert!(matches!(err("A;1"), ParseError::MissingParameters(_)));
+        assert!(matches!(err("A;x;10"), ParseError::InvalidBlockId(_)));
+        assert!(matches!(err("A;1;x"), ParseError::InvalidBlockSize(_)));
+        assert!(matches!(err("A;1;10;x"), ParseError::InvalidProcessId(_)));
+        assert!(matches!(err("D;-1"), ParseError::InvalidBlockId(_)));
+        assert!(matches!(err("D;1;2"), ParseError::Format(_)));
+        assert!(matches!(err("O;1"), ParseError::Format(_)));
+        assert!(matches!(err("W;1;7"), ParseError::Ignored(_)));
+        assert!(matches!(err("S;32;4096"), ParseError::Unsupported(_)));
+        assert!(matches!(err("K;1"), ParseError::Unsupported(_)));
+        assert!(matches!(err("X;1"), ParseError::InvalidCommand(_)));
     }
 }
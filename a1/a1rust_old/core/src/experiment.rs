@@ -0,0 +1,399 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    area::Area,
+    cmd::{Cmd, ParseError},
+    generator::Generator,
+    method::{AllocationMethod, MethodError, Registry},
+    output::{Format, Output, OutputError},
+    simulation::{Compaction, Run, Simulation, Simulator},
+};
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Unable to read config {0}: {1}")]
+    Io(String, std::io::Error),
+
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+
+    #[error("Unable to parse input {0}: {1}")]
+    Input(String, ParseError),
+
+    #[error("Reading from stdin needs {0}.")]
+    Stdin(String),
+
+    #[error("Experiment {0} has no inputs or generators.")]
+    NoInputs(String),
+
+    #[error("Unknown experiment {0}. [ Options are {1} ].")]
+    UnknownExperiment(String, String),
+
+    #[error(transparent)]
+    Method(#[from] MethodError),
+
+    #[error(transparent)]
+    Output(#[from] OutputError),
+}
+
+/// Output settings as they appear in the config, every one of them optional so an
+/// experiment only has to name what differs from the top level `[output]` table.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputConfig {
+    pub dir: Option<PathBuf>,
+    pub formats: Option<Vec<String>>,
+    pub per_method: Option<bool>,
+}
+
+impl OutputConfig {
+    fn or(&self, other: &OutputConfig) -> OutputConfig {
+        OutputConfig {
+            dir: self.dir.clone().or_else(|| other.dir.clone()),
+            formats: self.formats.clone().or_else(|| other.formats.clone()),
+            per_method: self.per_method.or(other.per_method),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExperimentConfig {
+    pub name: String,
+    #[serde(default)]
+    pub inputs: Vec<PathBuf>,
+    #[serde(default, rename = "generator")]
+    pub generators: Vec<Generator>,
+    /// Every registered method when not set.
+    pub methods: Option<Vec<String>>,
+    /// Memory sizes to run every input with instead of the size it declares, one run each.
+    #[serde(default)]
+    pub memory: Vec<u64>,
+    #[serde(default)]
+    pub compaction: Compaction,
+    #[serde(default)]
+    pub output: OutputConfig,
+}
+
+/// An experiments file:
+///
+/// ```toml
+/// [output]
+/// dir = "output"
+/// formats = ["text", "json"]
+///
+/// [[experiment]]
+/// name = "small"
+/// inputs = ["input/test.txt"]
+/// methods = ["first", "next"]
+/// memory = [800, 1000]
+/// compaction = "on-failure"
+///
+/// [[experiment.generator]]
+/// seed = 1
+/// commands = 1000
+/// memory = 4096
+/// ```
+///
+/// Relative paths are relative to the file itself.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub output: OutputConfig,
+    #[serde(default, rename = "experiment")]
+    pub experiments: Vec<ExperimentConfig>,
+}
+
+/// Settings from the command line or the environment, which win over the config.
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    pub inputs: Option<Vec<PathBuf>>,
+    pub methods: Option<Vec<AllocationMethod>>,
+    pub memory: Option<Vec<u64>>,
+    pub compaction: Option<Compaction>,
+    pub dir: Option<PathBuf>,
+    pub formats: Option<Vec<Format>>,
+    pub per_method: Option<bool>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Input {
+    File(PathBuf),
+    Generator(Generator),
+}
+
+impl Input {
+    fn load(&self) -> Result<(String, Area, Vec<Cmd>), ConfigError> {
+        match self {
+            Input::File(path) => {
+                let name = path.display().to_string();
+                let (area, cmds) =
+                    Run::from_file(&name).map_err(|err| ConfigError::Input(name.clone(), err))?;
+                Ok((name, area, cmds))
+            }
+            Input::Generator(generator) => {
+                let (area, cmds) = generator.generate();
+                Ok((generator.name(), area, cmds))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Experiment {
+    pub name: String,
+    pub inputs: Vec<Input>,
+    pub methods: Vec<AllocationMethod>,
+    pub memory: Vec<u64>,
+    pub compaction: Compaction,
+    pub output: Output,
+}
+
+impl Experiment {
+    /// One simulation per input and memory size, each running all of the methods.
+    pub fn simulator(&self) -> Result<Simulator, ConfigError> {
+        let mut simulations = vec![];
+        for input in self.inputs.iter() {
+            let (name, area, cmds) = input.load()?;
+            let cmds: Arc<[Cmd]> = cmds.into();
+            if self.memory.is_empty() {
+                simulations.push(Simulation::from_parts(
+                    &name,
+                    &area,
+                    Arc::clone(&cmds),
+                    &self.methods,
+                    self.compaction,
+                ));
+            }
+            for size in self.memory.iter() {
+                simulations.push(Simulation::from_parts(
+                    &format!("{}_{}", name, size),
+                    &Area::new(*size),
+                    Arc::clone(&cmds),
+                    &self.methods,
+                    self.compaction,
+                ));
+            }
+        }
+        Ok(Simulator::with_simulations(
+            simulations,
+            self.output.clone(),
+        ))
+    }
+
+    pub fn run(&self) -> Result<(), ConfigError> {
+        let _span = tracing::info_span!("experiment", name = %self.name).entered();
+        self.simulator()?.run()?;
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| ConfigError::Io(path.display().to_string(), err))?;
+        let mut config: Config = toml::from_str(&text)?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        config.output.dir = config.output.dir.map(|dir| base.join(dir));
+        for experiment in config.experiments.iter_mut() {
+            experiment.inputs = experiment.inputs.iter().map(|i| base.join(i)).collect();
+            experiment.output.dir = experiment.output.dir.as_ref().map(|dir| base.join(dir));
+        }
+        Ok(config)
+    }
+
+    /// Resolves the experiments named in `only`, or all of them when it is empty.
+    pub fn experiments(
+        &self,
+        registry: &Registry,
+        overrides: &Overrides,
+        only: &[&str],
+    ) -> Result<Vec<Experiment>, ConfigError> {
+        for name in only {
+            if !self.experiments.iter().any(|e| e.name == *name) {
+                let names: Vec<&str> = self.experiments.iter().map(|e| e.name.as_str()).collect();
+                return Err(ConfigError::UnknownExperiment(
+                    name.to_string(),
+                    names.join(", "),
+                ));
+            }
+        }
+        self.experiments
+            .iter()
+            .filter(|e| only.is_empty() || only.contains(&e.name.as_str()))
+            .map(|e| self.resolve(e, registry, overrides))
+            .collect()
+    }
+
+    fn resolve(
+        &self,
+        experiment: &ExperimentConfig,
+        registry: &Registry,
+        overrides: &Overrides,
+    ) -> Result<Experiment, ConfigError> {
+        let inputs: Vec<Input> = match &overrides.inputs {
+            Some(inputs) => inputs.iter().cloned().map(Input::File).collect(),
+            None => experiment
+                .inputs
+                .iter()
+                .cloned()
+                .map(Input::File)
+                .chain(experiment.generators.iter().cloned().map(Input::Generator))
+                .collect(),
+        };
+        if inputs.is_empty() {
+            return Err(ConfigError::NoInputs(experiment.name.clone()));
+        }
+
+        let methods = match (&overrides.methods, &experiment.methods) {
+            (Some(methods), _) => methods.clone(),
+            (None, Some(names)) => registry.select(names.iter().map(|n| n.as_str()))?,
+            (None, None) => registry.all(),
+        };
+
+        let output = experiment.output.or(&self.output);
+        let defaults = Output::default();
+        let formats = match (&overrides.formats, &output.formats) {
+            (Some(formats), _) => formats.clone(),
+            (None, Some(names)) => names
+                .iter()
+                .map(|n| n.parse())
+                .collect::<Result<Vec<Format>, OutputError>>()?,
+            (None, None) => defaults.formats,
+        };
+
+        Ok(Experiment {
+            name: experiment.name.clone(),
+            inputs,
+            methods,
+            memory: overrides
+                .memory
+                .clone()
+                .unwrap_or_else(|| experiment.memory.clone()),
+            compaction: overrides.compaction.unwrap_or(experiment.compaction),
+            output: Output {
+                dir: overrides.dir.clone().or(output.dir),
+                formats,
+                per_method: overrides
+                    .per_method
+                    .or(output.per_method)
+                    .unwrap_or(defaults.per_method),
+            },
+        })
+    }
+}
+
+mod experiment_tests {
+    #[cfg(test)]
+    const CONFIG: &str = r#"
+[output]
+dir = "output"
+formats = ["text"]
+
+[[experiment]]
+name = "files"
+inputs = ["input/test.txt"]
+methods = ["next", "first"]
+memory = [800, 1000]
+compaction = "on-failure"
+output = { formats = ["json"], per_method = true }
+
+[[experiment]]
+name = "generated"
+
+[[experiment.generator]]
+seed = 1
+commands = 50
+memory = 512
+"#;
+
+    #[test]
+    fn test_config() {
+        let config: super::Config = toml::from_str(CONFIG).unwrap();
+        let registry = super::Registry::default();
+        let experiments = config
+            .experiments(&registry, &super::Overrides::default(), &[])
+            .unwrap();
+        assert_eq!(experiments.len(), 2);
+
+        let files = &experiments[0];
+        let methods: Vec<&str> = files.methods.iter().map(|m| m.name).collect();
+        assert_eq!(methods, ["first", "next"]);
+        assert_eq!(files.memory, [800, 1000]);
+        assert_eq!(files.compaction, super::Compaction::OnFailure);
+        assert_eq!(files.output.dir, Some("output".into()));
+        assert_eq!(files.output.formats, [super::Format::Json]);
+        assert!(files.output.per_method);
+
+        let generated = &experiments[1];
+        assert_eq!(generated.methods.len(), registry.all().len());
+        assert_eq!(generated.output.formats, [super::Format::Text]);
+        assert!(matches!(generated.inputs[0], super::Input::Generator(_)));
+    }
+
+    #[test]
+    fn test_overrides() {
+        let config: super::Config = toml::from_str(CONFIG).unwrap();
+        let overrides = super::Overrides {
+            methods: Some(vec![super::AllocationMethod::BEST_FIT]),
+            memory: Some(vec![2000]),
+            compaction: Some(super::Compaction::Never),
+            dir: Some("ci".into()),
+            per_method: Some(false),
+            ..Default::default()
+        };
+        let experiments = config
+            .experiments(&super::Registry::default(), &overrides, &["files"])
+            .unwrap();
+        assert_eq!(experiments.len(), 1);
+        let files = &experiments[0];
+        assert_eq!(files.methods[0].name, "best");
+        assert_eq!(files.memory, [2000]);
+        assert_eq!(files.compaction, super::Compaction::Never);
+        assert_eq!(files.output.dir, Some("ci".into()));
+        assert!(!files.output.per_method);
+
+        assert!(config
+            .experiments(&super::Registry::default(), &overrides, &["missing"])
+            .is_err());
+    }
+
+    #[test]
+    fn test_simulator_matrix() {
+        let config: super::Config = toml::from_str(
+            r#"
+[[experiment]]
+name = "matrix"
+inputs = ["../input/test.txt"]
+memory = [800, 1000]
+
+[[experiment.generator]]
+seed = 1
+commands = 50
+memory = 512
+"#,
+        )
+        .unwrap();
+        let experiments = config
+            .experiments(&super::Registry::default(), &Default::default(), &[])
+            .unwrap();
+        let simulator = experiments[0].simulator().unwrap();
+        let names: Vec<&str> = simulator.simulations().iter().map(|s| s.name()).collect();
+        assert_eq!(
+            names,
+            [
+                "../input/test.txt_800",
+                "../input/test.txt_1000",
+                "generated_1_50_800",
+                "generated_1_50_1000"
+            ]
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use serde::Deserialize;
+
+use crate::{area::Area, cmd::Cmd};
+
+/// Describes a reproducible random trace, so experiments do not need an input file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Generator {
+    pub seed: u64,
+    pub commands: usize,
+    pub memory: u64,
+    /// Allocation sizes are drawn from `1..=max_size`.
+    #[serde(default = "Generator::default_max_size")]
+    pub max_size: u64,
+}
+
+impl Generator {
+    fn default_max_size() -> u64 {
+        64
+    }
+
+    /// Stands in for the file name in the output files.
+    pub fn name(&self) -> String {
+        format!("generated_{}_{}", self.seed, self.commands)
+    }
+
+    /// Allocates about twice as often as it frees, and only ever frees blocks that are live.
+    pub fn generate(&self) -> (Area, Vec<Cmd>) {
+        let mut seed = self.seed;
+        let mut next = move || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            seed >> 33
+        };
+        let mut live: Vec<u64> = vec![];
+        let mut cmds = Vec::with_capacity(self.commands);
+        for id in 0..self.commands as u64 {
+            if live.is_empty() || next() % 3 != 0 {
+                cmds.push(Cmd::alloc(id, 1 + next() % self.max_size.max(1)));
+                live.push(id);
+            } else {
+                let idx = (next() as usize) % live.len();
+                cmds.push(Cmd::dealloc(live.swap_remove(idx)));
+            }
+        }
+        (Area::new(self.memory), cmds)
+    }
+}
+
+mod generator_tests {
+
+    #[test]
+    fn test_generate() {
+        let generator = super::Generator {
+            seed: 7,
+            commands: 100,
+            memory: 1000,
+            max_size: 10,
+        };
+        let (area, cmds) = generator.generate();
+        assert_eq!(area.size, 1000);
+        assert_eq!(cmds.len(), 100);
+        assert!(cmds.iter().all(|c| c.size.unwrap_or(1) <= 10));
+
+        let (_, again) = generator.generate();
+        let ids = |cmds: &[crate::cmd::Cmd]| -> Vec<Option<u64>> {
+            cmds.iter().map(|c| c.block_id).collect()
+        };
+        assert_eq!(ids(&cmds), ids(&again));
+    }
+}
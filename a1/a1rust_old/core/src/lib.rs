@@ -1,4 +1,10 @@
 pub mod area;
+pub mod bitmap;
 pub mod block;
 pub mod cmd;
+pub mod experiment;
+pub mod generator;
+pub mod method;
+pub mod output;
+pub mod select;
 pub mod simulation;
@@ -0,0 +1,178 @@
+use thiserror::Error;
+
+use crate::area::{AResult, Area};
+
+/// Allocates `size` bytes for a block id in the area.
+pub type Alloc = fn(&mut Area, u64, u64) -> AResult<()>;
+
+#[derive(Debug, Error)]
+pub enum MethodError {
+    #[error("Unknown allocation method {0}. [ Options are {1} ].")]
+    Unknown(String, String),
+
+    #[error("An allocation method named {0} is already registered.")]
+    Duplicate(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AllocationMethod {
+    /// What the method is selected by on the command line, e.g. `first`.
+    pub name: &'static str,
+    /// What the method is called in the output, e.g. `First Fit`.
+    pub label: &'static str,
+    pub alloc: Alloc,
+}
+
+impl AllocationMethod {
+    pub const FIRST_FIT: Self = Self::new("first", "First Fit", Area::alloc_first_fit);
+    pub const BEST_FIT: Self = Self::new("best", "Best Fit", Area::alloc_best_fit);
+    pub const WORST_FIT: Self = Self::new("worst", "Worst Fit", Area::alloc_worst_fit);
+    pub const NEXT_FIT: Self = Self::new("next", "Next Fit", Area::alloc_next_fit);
+    pub const BITMAP: Self = Self::new("bitmap", "Bitmap", Area::alloc_bitmap);
+
+    pub const fn new(name: &'static str, label: &'static str, alloc: Alloc) -> Self {
+        Self { name, label, alloc }
+    }
+
+    pub fn alloc(&self, area: &mut Area, block_id: u64, size: u64) -> AResult<()> {
+        (self.alloc)(area, block_id, size)
+    }
+}
+
+impl Default for AllocationMethod {
+    fn default() -> Self {
+        Self::FIRST_FIT
+    }
+}
+
+impl std::fmt::Display for AllocationMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.label)
+    }
+}
+
+/// The allocation methods a simulation can be run with, in the order they are reported.
+#[derive(Debug, Clone)]
+pub struct Registry(Vec<AllocationMethod>);
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self(vec![
+            AllocationMethod::FIRST_FIT,
+            AllocationMethod::BEST_FIT,
+            AllocationMethod::WORST_FIT,
+            AllocationMethod::NEXT_FIT,
+            AllocationMethod::BITMAP,
+        ])
+    }
+}
+
+impl Registry {
+    /// Selects every registered method.
+    pub const ALL: &'static str = "all";
+
+    pub fn register(&mut self, method: AllocationMethod) -> Result<(), MethodError> {
+        if method.name == Self::ALL || self.get(method.name).is_some() {
+            return Err(MethodError::Duplicate(method.name.to_owned()));
+        }
+        self.0.push(method);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<AllocationMethod> {
+        self.0.iter().find(|m| m.name == name).copied()
+    }
+
+    pub fn all(&self) -> Vec<AllocationMethod> {
+        self.0.clone()
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.0.iter().map(|m| m.name).collect()
+    }
+
+    /// Looks up every name in registry order, so `worst,first` and `first,worst` run alike.
+    /// Names that are given twice only run once, and `all` stands for every method.
+    pub fn select<'a, I: IntoIterator<Item = &'a str>>(
+        &self,
+        names: I,
+    ) -> Result<Vec<AllocationMethod>, MethodError> {
+        let mut selected = vec![];
+        for name in names {
+            let name = name.trim();
+            if name == Self::ALL {
+                selected.extend(self.names());
+                continue;
+            }
+            match self.get(name) {
+                Some(_) => selected.push(name),
+                None => {
+                    return Err(MethodError::Unknown(
+                        name.to_owned(),
+                        [self.names(), vec![Self::ALL]].concat().join(", "),
+                    ))
+                }
+            }
+        }
+        Ok(self
+            .0
+            .iter()
+            .filter(|m| selected.contains(&m.name))
+            .copied()
+            .collect())
+    }
+}
+
+mod method_tests {
+
+    #[test]
+    fn test_select() {
+        let registry = super::Registry::default();
+        let names = |methods: Vec<super::AllocationMethod>| -> Vec<&str> {
+            methods.iter().map(|m| m.name).collect()
+        };
+        assert_eq!(
+            names(registry.select(["next", "first", "worst"]).unwrap()),
+            ["first", "worst", "next"]
+        );
+        assert_eq!(
+            names(registry.select("best,best".split(',')).unwrap()),
+            ["best"]
+        );
+        assert!(registry.select(["first", "last"]).is_err());
+        assert!(registry.select(["all", "last"]).is_err());
+        assert_eq!(
+            names(registry.select(["worst", "all"]).unwrap()),
+            registry.names()
+        );
+    }
+
+    #[test]
+    fn test_register() {
+        let mut registry = super::Registry::default();
+        let custom = super::AllocationMethod::new("custom", "Custom", super::Area::alloc_best_fit);
+        registry.register(custom).unwrap();
+        assert!(registry.register(custom).is_err());
+        let all = super::AllocationMethod::new("all", "All", super::Area::alloc_best_fit);
+        assert!(registry.register(all).is_err());
+        assert_eq!(registry.get("custom").unwrap().label, "Custom");
+        assert_eq!(registry.all().len(), 6);
+    }
+
+    #[test]
+    fn test_next_fit() {
+        let mut area = super::Area::new(100);
+        let next = super::AllocationMethod::NEXT_FIT;
+        next.alloc(&mut area, 0, 10).unwrap();
+        next.alloc(&mut area, 1, 10).unwrap();
+        next.alloc(&mut area, 2, 10).unwrap();
+        area.dealloc(0).unwrap();
+        // The hole at the start is skipped, the search resumes after block 2.
+        next.alloc(&mut area, 3, 10).unwrap();
+        assert_eq!(area.used_blocks.last().unwrap().start_addr, 30);
+        next.alloc(&mut area, 4, 60).unwrap();
+        // Nothing fits after block 4, so the search wraps around to the start.
+        next.alloc(&mut area, 5, 10).unwrap();
+        assert_eq!(area.used_blocks.last().unwrap().start_addr, 0);
+    }
+}
@@ -0,0 +1,375 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    area::Area,
+    block::{Block, BlockId},
+    method::AllocationMethod,
+    simulation::{Checkpoint, Outcome},
+};
+
+#[derive(Debug, Error)]
+pub enum OutputError {
+    #[error("Unknown output format {0}. [ Options are text, json and debug ].")]
+    Format(String),
+
+    #[error("Unable to write {0}: {1}")]
+    Io(String, std::io::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// The used and free blocks and the fragmentation, written to `<name>_out.txt`.
+    Text,
+    /// The same report as json, written to `<name>_out.json`.
+    Json,
+    /// The final `Area` of every method as its `Debug` output, written to `<name>_raw_out.txt`.
+    Debug,
+}
+
+impl Format {
+    fn suffix(&self) -> &'static str {
+        match self {
+            Self::Text => "out.txt",
+            Self::Json => "out.json",
+            Self::Debug => "raw_out.txt",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = OutputError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "debug" => Ok(Self::Debug),
+            _ => Err(OutputError::Format(s.to_owned())),
+        }
+    }
+}
+
+/// Where the results of a simulation go and what they look like.
+#[derive(Debug, Clone)]
+pub struct Output {
+    /// Written next to the input file when not set.
+    pub dir: Option<PathBuf>,
+    pub formats: Vec<Format>,
+    /// One file per method and format, `<name>_<method>_out.txt`, instead of one per format.
+    pub per_method: bool,
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            formats: vec![Format::Text, Format::Debug],
+            per_method: false,
+        }
+    }
+}
+
+impl Output {
+    fn path(&self, file: &str, method: Option<&AllocationMethod>, format: Format) -> PathBuf {
+        let name = match method {
+            Some(method) => format!("{}_{}_{}", file, method.name, format.suffix()),
+            None => format!("{}_{}", file, format.suffix()),
+        };
+        match &self.dir {
+            Some(dir) => {
+                let name = Path::new(&name).file_name().unwrap_or_default();
+                dir.join(name)
+            }
+            None => PathBuf::from(name),
+        }
+    }
+
+    /// Writes the final areas of every method run on `file`, each output file exactly once.
+    pub fn write(&self, file: &str, results: &[Outcome]) -> Result<Vec<PathBuf>, OutputError> {
+        let mut written = vec![];
+        if let Some(dir) = &self.dir {
+            std::fs::create_dir_all(dir)
+                .map_err(|err| OutputError::Io(dir.display().to_string(), err))?;
+        }
+        for format in self.formats.iter() {
+            if self.per_method {
+                for result in results {
+                    let path = self.path(file, Some(&result.method), *format);
+                    write_file(&path, &render(*format, std::slice::from_ref(result))?)?;
+                    written.push(path);
+                }
+            } else {
+                let path = self.path(file, None, *format);
+                write_file(&path, &render(*format, results)?)?;
+                written.push(path);
+            }
+        }
+        Ok(written)
+    }
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<(), OutputError> {
+    std::fs::File::create(path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map_err(|err| OutputError::Io(path.display().to_string(), err))
+}
+
+#[derive(Serialize)]
+struct BlockReport {
+    id: Option<u64>,
+    start_addr: u64,
+    end_addr: u64,
+    size: u64,
+}
+
+impl From<&Block> for BlockReport {
+    fn from(block: &Block) -> Self {
+        Self {
+            id: match block.id {
+                BlockId::Used(id) => Some(id),
+                BlockId::Free => None,
+            },
+            start_addr: block.start_addr,
+            end_addr: block.end_addr,
+            size: block.size,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct BitmapReport {
+    unit: u64,
+    overhead: u64,
+    rounding: u64,
+}
+
+#[derive(Serialize)]
+struct AreaReport {
+    size: u64,
+    used_blocks: Vec<BlockReport>,
+    free_blocks: Vec<BlockReport>,
+    fragmentation: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bitmap: Option<BitmapReport>,
+}
+
+impl From<&Area> for AreaReport {
+    fn from(area: &Area) -> Self {
+        Self {
+            size: area.size,
+            used_blocks: area.used_blocks.iter().map(BlockReport::from).collect(),
+            free_blocks: area.free_blocks.iter().map(BlockReport::from).collect(),
+            fragmentation: area.fragmentation(),
+            bitmap: area.bitmap.as_ref().map(|bitmap| BitmapReport {
+                unit: bitmap.unit,
+                overhead: bitmap.overhead(),
+                rounding: bitmap.rounding(&area.used_blocks),
+            }),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CheckpointReport {
+    index: usize,
+    #[serde(flatten)]
+    area: AreaReport,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    method: &'a str,
+    label: &'a str,
+    #[serde(flatten)]
+    area: AreaReport,
+    checkpoints: Vec<CheckpointReport>,
+}
+
+/// Every checkpoint of a method comes right before its final area.
+pub fn render(format: Format, results: &[Outcome]) -> Result<String, OutputError> {
+    let mut out = String::new();
+    match format {
+        Format::Text => {
+            for outcome in results {
+                for checkpoint in outcome.checkpoints.iter() {
+                    out.push_str(&self::checkpoint(&outcome.method, checkpoint));
+                }
+                out.push_str(&text(outcome.method.label, &outcome.area));
+            }
+        }
+        Format::Json => {
+            let reports: Vec<Report> = results
+                .iter()
+                .map(|outcome| Report {
+                    method: outcome.method.name,
+                    label: outcome.method.label,
+                    area: AreaReport::from(&outcome.area),
+                    checkpoints: outcome
+                        .checkpoints
+                        .iter()
+                        .map(|c| CheckpointReport {
+                            index: c.index,
+                            area: AreaReport::from(&c.area),
+                        })
+                        .collect(),
+                })
+                .collect();
+            out = serde_json::to_string_pretty(&reports)?;
+            out.push('\n');
+        }
+        Format::Debug => {
+            for outcome in results {
+                for checkpoint in outcome.checkpoints.iter() {
+                    out.push_str(&format!(
+                        "{}\n{:#?}\n",
+                        checkpoint_label(&outcome.method, checkpoint),
+                        checkpoint.area
+                    ));
+                }
+                out.push_str(&format!("{}\n{:#?}\n", outcome.method, outcome.area));
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn checkpoint_label(method: &AllocationMethod, checkpoint: &Checkpoint) -> String {
+    format!("{} at command {}", method, checkpoint.index)
+}
+
+/// The text report of the area at an `O` command.
+pub fn checkpoint(method: &AllocationMethod, checkpoint: &Checkpoint) -> String {
+    text(&checkpoint_label(method, checkpoint), &checkpoint.area)
+}
+
+fn text(label: &str, area: &Area) -> String {
+    let blocks = |blocks: &[Block]| {
+        blocks
+            .iter()
+            .map(|b| b.to_string())
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+    let mut text = format!(
+        "{}\nUsed Blocks:\n{}\nFree Blocks:\n{}\nFragmentation:\n{}\n",
+        label,
+        blocks(&area.used_blocks),
+        blocks(&area.free_blocks),
+        area.fragmentation()
+    );
+    if let Some(bitmap) = &area.bitmap {
+        text.push_str(&format!(
+            "Bitmap Overhead:\n{}\nRounding:\n{}\n",
+            bitmap.overhead(),
+            bitmap.rounding(&area.used_blocks)
+        ));
+    }
+    text
+}
+
+mod output_tests {
+    #[cfg(test)]
+    use super::{Format, Output};
+    #[cfg(test)]
+    use crate::{
+        area::Area,
+        method::AllocationMethod,
+        simulation::{Checkpoint, Outcome},
+    };
+
+    #[cfg(test)]
+    fn results() -> Vec<Outcome> {
+        [AllocationMethod::FIRST_FIT, AllocationMethod::WORST_FIT]
+            .into_iter()
+            .map(|method| {
+                let mut area = Area::new(100);
+                method.alloc(&mut area, 0, 10).unwrap();
+                let checkpoints = vec![Checkpoint {
+                    index: 1,
+                    area: area.clone(),
+                }];
+                method.alloc(&mut area, 1, 20).unwrap();
+                Outcome {
+                    method,
+                    area,
+                    checkpoints,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_render() {
+        let text = super::render(Format::Text, &results()).unwrap();
+        assert!(
+            text.starts_with("First Fit at command 1\nUsed Blocks:\n0;0;9\nFree Blocks:\n10;99\n")
+        );
+        assert!(text.contains("First Fit\nUsed Blocks:\n0;0;9\n1;10;29\nFree Blocks:\n30;99\n"));
+        assert!(text.contains("Worst Fit\n"));
+
+        let debug = super::render(Format::Debug, &results()).unwrap();
+        assert_eq!(debug.matches("Area {").count(), 4);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&super::render(Format::Json, &results()).unwrap()).unwrap();
+        assert_eq!(json[1]["method"], "worst");
+        assert_eq!(json[0]["used_blocks"][0]["end_addr"], 9);
+        assert_eq!(json[0]["free_blocks"][0]["id"], serde_json::Value::Null);
+        assert_eq!(json[0]["checkpoints"][0]["index"], 1);
+        assert_eq!(
+            json[0]["checkpoints"][0]["free_blocks"][0]["start_addr"],
+            10
+        );
+        assert!(json[0].get("bitmap").is_none());
+    }
+
+    #[test]
+    fn test_render_bitmap() {
+        let method = AllocationMethod::BITMAP;
+        let mut area = Area::new(100);
+        method.alloc(&mut area, 0, 10).unwrap();
+        method.alloc(&mut area, 1, 3).unwrap();
+        let results = [Outcome {
+            method,
+            area,
+            checkpoints: vec![],
+        }];
+        let text = super::render(Format::Text, &results).unwrap();
+        assert!(text.starts_with("Bitmap\nUsed Blocks:\n0;0;15\n1;16;23\n"));
+        assert!(text.ends_with("Bitmap Overhead:\n2\nRounding:\n11\n"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&super::render(Format::Json, &results).unwrap()).unwrap();
+        assert_eq!(json[0]["bitmap"]["unit"], 8);
+        assert_eq!(json[0]["bitmap"]["rounding"], 11);
+    }
+
+    #[test]
+    fn test_paths() {
+        let output = Output {
+            dir: Some("out".into()),
+            formats: vec![Format::Json],
+            per_method: true,
+        };
+        let method = AllocationMethod::BEST_FIT;
+        assert_eq!(
+            output.path("input/test.txt", Some(&method), Format::Json),
+            std::path::PathBuf::from("out/test.txt_best_out.json")
+        );
+        assert_eq!(
+            Output::default().path("input/test.txt", None, Format::Debug),
+            std::path::PathBuf::from("input/test.txt_raw_out.txt")
+        );
+        assert!("yaml".parse::<Format>().is_err());
+    }
+}
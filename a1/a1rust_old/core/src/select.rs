@@ -0,0 +1,59 @@
+use crate::block::Block;
+
+/// Picks the free block an allocation of `size` bytes goes into, by its index in `blocks`.
+pub type Select = fn(&[Block], u64) -> Option<usize>;
+
+pub fn first_fit(blocks: &[Block], size: u64) -> Option<usize> {
+    blocks.iter().position(|b| b.size >= size)
+}
+
+pub fn best_fit(blocks: &[Block], size: u64) -> Option<usize> {
+    blocks
+        .iter()
+        .enumerate()
+        .filter(|(_, b)| b.size >= size)
+        .min_by_key(|(_, b)| b.size)
+        .map(|(i, _)| i)
+}
+
+pub fn worst_fit(blocks: &[Block], size: u64) -> Option<usize> {
+    blocks
+        .iter()
+        .enumerate()
+        .filter(|(_, b)| b.size >= size)
+        .max_by_key(|(_, b)| b.size)
+        .map(|(i, _)| i)
+}
+
+/// The first fitting block at or after `cursor`, wrapping around to the start of the area.
+pub fn next_fit(blocks: &[Block], size: u64, cursor: u64) -> Option<usize> {
+    blocks
+        .iter()
+        .enumerate()
+        .filter(|(_, b)| b.size >= size)
+        .min_by_key(|(_, b)| (b.end_addr < cursor, b.start_addr))
+        .map(|(i, _)| i)
+}
+
+mod select_tests {
+
+    #[test]
+    fn test_selection() {
+        let blocks = vec![
+            super::Block::new_free(0, 30),
+            super::Block::new_free(40, 10),
+            super::Block::new_free(60, 50),
+            super::Block::new_free(120, 10),
+        ];
+        assert_eq!(super::first_fit(&blocks, 10), Some(0));
+        assert_eq!(super::best_fit(&blocks, 10), Some(1));
+        assert_eq!(super::worst_fit(&blocks, 10), Some(2));
+        assert_eq!(super::first_fit(&blocks, 40), Some(2));
+        assert_eq!(super::best_fit(&blocks, 60), None);
+        assert_eq!(super::worst_fit(&blocks, 60), None);
+        assert_eq!(super::next_fit(&blocks, 10, 0), Some(0));
+        assert_eq!(super::next_fit(&blocks, 10, 45), Some(1));
+        assert_eq!(super::next_fit(&blocks, 10, 55), Some(2));
+        assert_eq!(super::next_fit(&blocks, 20, 115), Some(0));
+    }
+}
@@ -1,137 +1,241 @@
-use std::io::Write;
+use std::{
+    io::{BufRead, Write},
+    str::FromStr,
+    sync::Arc,
+};
+
+use rayon::prelude::*;
+use serde::Deserialize;
 
 use crate::{
-    area::Area,
+    area::{AError, AResult, Area},
     cmd::{Cmd, CmdType, ParseError, ParseResult},
+    method::AllocationMethod,
+    output::{self, Output, OutputError},
 };
 
-pub struct Simulator(Vec<Simulation>);
+/// The area at an `O` command, `index` being its position among the commands that are run.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub index: usize,
+    pub area: Area,
+}
+
+/// What a run of one method leaves behind.
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub method: AllocationMethod,
+    pub area: Area,
+    pub checkpoints: Vec<Checkpoint>,
+}
+
+pub struct Simulator(Vec<Simulation>, Output);
 
 impl Simulator {
-    pub fn new(files: Vec<&str>, run_option: RunOption) -> Self {
-        let mut simulations = vec![];
-        for file in files {
-            let simulation = Simulation::new(file, run_option.clone());
-            simulations.push(simulation);
-        }
-        Self(simulations)
+    pub fn new(
+        files: Vec<&str>,
+        methods: Vec<AllocationMethod>,
+        output: Output,
+    ) -> ParseResult<Self> {
+        let simulations = files
+            .into_iter()
+            .map(|file| Simulation::new(file, &methods))
+            .collect::<ParseResult<Vec<Simulation>>>()?;
+        Ok(Self(simulations, output))
     }
 
-    pub fn run(&mut self) {
-        for simulation in self.0.iter_mut() {
-            simulation.run();
-        }
+    pub fn with_simulations(simulations: Vec<Simulation>, output: Output) -> Self {
+        Self(simulations, output)
     }
-}
 
-#[derive(Debug, Clone)]
-pub enum RunOption {
-    All,
-    FirstFit,
-    BestFit,
-    WorstFit,
-}
+    pub fn simulations(&self) -> &[Simulation] {
+        &self.0
+    }
 
-impl From<(bool, bool, bool, bool)> for RunOption {
-    fn from((all, first, best, worst): (bool, bool, bool, bool)) -> Self {
-        if all {
-            Self::All
-        } else if first {
-            Self::FirstFit
-        } else if best {
-            Self::BestFit
-        } else if worst {
-            Self::WorstFit
-        } else {
-            Self::FirstFit
+    /// Runs every method of every simulation on the rayon pool, then writes the results
+    /// one simulation after another, so the files come out in input order. The events the
+    /// runs log are emitted by the workers as they go and interleave across runs, only the
+    /// `written` events follow the input order.
+    pub fn run(&mut self) -> Result<(), OutputError> {
+        let span = tracing::Span::current();
+        let results = self
+            .0
+            .par_iter_mut()
+            .map(|simulation| {
+                let _span = span.enter();
+                simulation.results()
+            })
+            .collect::<Vec<Vec<Outcome>>>();
+        for (simulation, results) in self.0.iter().zip(results.iter()) {
+            simulation.write(&self.1, results)?;
         }
+        Ok(())
     }
 }
 
 pub struct Simulation(Vec<Run>, String);
 
 impl Simulation {
-    pub fn new(file: &str, ty: RunOption) -> Self {
-        let runs = match ty {
-            RunOption::All => {
-                let first_fit_run = Run::new(file, AllocationMethod::FirstFit)
-                    .expect("Failed to create first-fit run");
-                let best_fit_run = Run::new(file, AllocationMethod::BestFit)
-                    .expect("Failed to create best-fit run");
-                let worst_fit_run = Run::new(file, AllocationMethod::WorstFit)
-                    .expect("Failed to create worst-fit run");
-                vec![first_fit_run, best_fit_run, worst_fit_run]
-            }
-            RunOption::FirstFit => {
-                let first_fit_run = Run::new(file, AllocationMethod::FirstFit)
-                    .expect("Failed to create first-fit run");
-                vec![first_fit_run]
-            }
-            RunOption::BestFit => {
-                let best_fit_run = Run::new(file, AllocationMethod::BestFit)
-                    .expect("Failed to create best-fit run");
-                vec![best_fit_run]
-            }
-            RunOption::WorstFit => {
-                let worst_fit_run = Run::new(file, AllocationMethod::WorstFit)
-                    .expect("Failed to create worst-fit run");
-                vec![worst_fit_run]
-            }
-        };
-        Simulation(runs, file.to_owned())
+    /// Parses `file` once, all of the methods share the commands.
+    pub fn new(file: &str, methods: &[AllocationMethod]) -> ParseResult<Self> {
+        let (area, cmds) = Run::from_file(file)?;
+        Ok(Self::from_parts(
+            file,
+            &area,
+            cmds.into(),
+            methods,
+            Compaction::default(),
+        ))
     }
 
-    pub fn run(&mut self) {
-        let areas = self
-            .0
-            .iter_mut()
-            .map(|r| r.run())
-            .collect::<Vec<(Area, AllocationMethod)>>();
-        let mut out_string = String::new();
-        for (area, method) in areas.iter() {
-            let method_str = method.to_string();
-            let used_blocks = area
-                .used_blocks
-                .iter()
-                .map(|b| b.to_string())
-                .collect::<Vec<String>>()
-                .join("\n");
-            let free_blocks = area
-                .free_blocks
-                .iter()
-                .map(|b| b.to_string())
-                .collect::<Vec<String>>()
-                .join("\n");
-            let fragmentation = area.fragmentation();
-            out_string.push_str(&format!(
-                "{}\nUsed Blocks:\n{}\nFree Blocks:\n{}\nFragmentation:\n{}\n",
-                method_str, used_blocks, free_blocks, fragmentation
-            ));
-            let file_name = self.1.clone();
-            let mut out_file = std::fs::File::create(format!("{}_out.txt", file_name)).unwrap();
-            let mut raw_file = std::fs::File::create(format!("{}_raw_out.txt", file_name)).unwrap();
-            out_file.write_all(out_string.as_bytes()).unwrap();
-            raw_file
-                .write_all(format!("{:#?}", area).as_bytes())
-                .unwrap();
+    pub fn name(&self) -> &str {
+        &self.1
+    }
+
+    /// Runs every method on its own copy of the area, over the same commands. `name` stands
+    /// in for the file name in the output files.
+    pub fn from_parts(
+        name: &str,
+        area: &Area,
+        cmds: Arc<[Cmd]>,
+        methods: &[AllocationMethod],
+        compaction: Compaction,
+    ) -> Self {
+        let runs = methods
+            .iter()
+            .map(|method| Run {
+                method: *method,
+                area: area.clone(),
+                cmds: Arc::clone(&cmds),
+                compaction,
+                checkpoints: vec![],
+            })
+            .collect();
+        Simulation(runs, name.to_owned())
+    }
+
+    /// The final area of every method, in the order the methods were given.
+    pub fn results(&mut self) -> Vec<Outcome> {
+        let span = tracing::Span::current();
+        self.0
+            .par_iter_mut()
+            .map(|r| {
+                let _span = span.enter();
+                r.run()
+            })
+            .collect()
+    }
+
+    pub fn write(&self, output: &Output, results: &[Outcome]) -> Result<(), OutputError> {
+        for path in output.write(&self.1, results)? {
+            tracing::info!(path = %path.display(), "written");
+        }
+        Ok(())
+    }
+
+    pub fn run(&mut self, output: &Output) -> Result<(), OutputError> {
+        let results = self.results();
+        self.write(output, &results)
+    }
+}
+
+// Commands only a1rust has a model for and that leave the blocks alone are skipped with a
+// warning, so its traces run here too. They do not count, `index` is the position among the
+// commands that are run. Those that would change the blocks are rejected like any bad command.
+fn parse_cmd(index: usize, token: &str) -> ParseResult<Option<Cmd>> {
+    match Cmd::try_from(token) {
+        Ok(cmd) => Ok(Some(cmd)),
+        Err(err @ ParseError::Ignored(_)) => {
+            tracing::warn!(index, "{}", err);
+            Ok(None)
+        }
+        Err(err) => Err(ParseError::At(index, Box::new(err))),
+    }
+}
+
+fn memory_size(token: &str) -> ParseResult<u64> {
+    match token.parse() {
+        Ok(0) | Err(_) => Err(ParseError::InvalidMemorySize(token.to_owned())),
+        Ok(size) => Ok(size),
+    }
+}
+
+/// Runs every method on the commands as they are read from `input`, without keeping them.
+/// The memory size is `size`, or the first token without it. A command the area rejects is
+/// written to `out` right away as `method;index;error`, and so is the area at every `O`,
+/// which is not kept for the outcome.
+pub fn stream<R: BufRead, W: Write>(
+    input: R,
+    out: &mut W,
+    methods: &[AllocationMethod],
+    size: Option<u64>,
+    compaction: Compaction,
+) -> ParseResult<Vec<Outcome>> {
+    let start = |size: u64| -> Vec<Run> {
+        methods
+            .iter()
+            .map(|method| Run {
+                method: *method,
+                area: Area::new(size),
+                cmds: Arc::from(vec![]),
+                compaction,
+                checkpoints: vec![],
+            })
+            .collect()
+    };
+    let mut runs = size.map(start);
+    let mut index = 0;
+    for line in input.lines() {
+        for token in line?.split_whitespace() {
+            let Some(runs) = runs.as_mut() else {
+                runs = Some(start(memory_size(token)?));
+                continue;
+            };
+            let Some(cmd) = parse_cmd(index, token)? else {
+                continue;
+            };
+            for run in runs.iter_mut() {
+                if let Err(err) = run.step(index, &cmd) {
+                    writeln!(out, "{};{};{}", run.method, index, err)?;
+                }
+                if let Some(checkpoint) = run.checkpoints.pop() {
+                    write!(out, "{}", output::checkpoint(&run.method, &checkpoint))?;
+                }
+            }
+            index += 1;
         }
+        out.flush()?;
+    }
+    match runs {
+        Some(runs) => Ok(runs.into_iter().map(|r| r.outcome()).collect()),
+        None => Err(ParseError::MissingSize),
     }
 }
 
-#[derive(Debug, Default, Clone)]
-pub enum AllocationMethod {
+/// When a run compacts the area on its own, besides the `C` commands in the input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Compaction {
     #[default]
-    FirstFit,
-    BestFit,
-    WorstFit,
+    Never,
+    /// Compacts and retries once when an allocation does not fit.
+    OnFailure,
+    /// Compacts after every deallocation, so there is never more than one hole.
+    AfterDealloc,
 }
 
-impl std::fmt::Display for AllocationMethod {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::FirstFit => write!(f, "First Fit"),
-            Self::BestFit => write!(f, "Best Fit"),
-            Self::WorstFit => write!(f, "Worst Fit"),
+impl FromStr for Compaction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "never" => Ok(Self::Never),
+            "on-failure" => Ok(Self::OnFailure),
+            "after-dealloc" => Ok(Self::AfterDealloc),
+            _ => Err(format!(
+                "Unknown compaction policy {}. [ Options are never, on-failure and after-dealloc ].",
+                s
+            )),
         }
     }
 }
@@ -139,14 +243,22 @@ impl std::fmt::Display for AllocationMethod {
 pub struct Run {
     pub method: AllocationMethod,
     pub area: Area,
-    pub cmds: Vec<Cmd>,
+    pub cmds: Arc<[Cmd]>,
+    pub compaction: Compaction,
+    pub checkpoints: Vec<Checkpoint>,
 }
 
 impl Run {
     pub fn new(file: &str, method: AllocationMethod) -> Result<Self, ParseError> {
         let res = Self::from_file(file);
         match res {
-            Ok((area, cmds)) => Ok(Run { method, area, cmds }),
+            Ok((area, cmds)) => Ok(Run {
+                method,
+                area,
+                cmds: cmds.into(),
+                compaction: Compaction::default(),
+                checkpoints: vec![],
+            }),
             Err(err) => Err(err),
         }
     }
@@ -156,70 +268,112 @@ impl Run {
         Self::from_str(&inp)
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(inp: &str) -> ParseResult<(Area, Vec<Cmd>)> {
-        let itr = inp.split_whitespace().into_iter();
+        let itr = inp.split_whitespace();
         let size = itr.clone().next();
         match size {
             Some(size) => {
-                let size = size.parse()?;
-                let area = Area::new(size);
+                let area = Area::new(memory_size(size)?);
                 let mut cmds = Vec::new();
-                for cmd in itr.skip(1) {
-                    let cmd = Cmd::try_from(cmd)?;
-                    cmds.push(cmd);
+                for token in itr.skip(1) {
+                    if let Some(cmd) = parse_cmd(cmds.len(), token)? {
+                        cmds.push(cmd);
+                    }
                 }
                 Ok((area, cmds))
             }
-            None => Err(ParseError::MissingParameters("size".to_owned())),
+            None => Err(ParseError::MissingSize),
         }
     }
 
-    pub fn run(&mut self) -> (Area, AllocationMethod) {
-        for cmd in self.cmds.iter() {
-            match cmd.ty {
-                CmdType::Alloc => {
-                    println!(
-                        "Allocating block {} with {} bytes",
-                        cmd.block_id.unwrap(),
-                        cmd.size.unwrap()
-                    );
-                    let block_id = cmd.block_id.unwrap();
-                    let size = cmd.size.unwrap();
-                    let res = match self.method {
-                        AllocationMethod::FirstFit => self.area.alloc_first_fit(block_id, size),
-                        AllocationMethod::BestFit => self.area.alloc_best_fit(block_id, size),
-                        AllocationMethod::WorstFit => self.area.alloc_worst_fit(block_id, size),
-                    };
-                    match res {
-                        Ok(_) => (),
-                        Err(err) => println!("Error: {}", err),
-                    }
-                }
-                CmdType::Dealloc => {
-                    println!("Deallocating block {}.", cmd.block_id.unwrap());
-                    let block_id = cmd.block_id.unwrap();
-                    match self.area.dealloc(block_id) {
-                        Ok(_) => (),
-                        Err(err) => println!("Error: {}", err),
-                    }
-                }
-                CmdType::Compact => match self.area.compact() {
-                    Ok(_) => (),
-                    Err(err) => println!("Error: {}", err),
-                },
+    pub fn run(&mut self) -> Outcome {
+        let cmds = Arc::clone(&self.cmds);
+        for (index, cmd) in cmds.iter().enumerate() {
+            let _ = self.step(index, cmd);
+        }
+        tracing::info!(
+            method = self.method.name,
+            commands = self.cmds.len(),
+            fragmentation = self.area.fragmentation(),
+            "finished"
+        );
+        self.outcome()
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        Outcome {
+            method: self.method,
+            area: self.area.clone(),
+            checkpoints: self.checkpoints.clone(),
+        }
+    }
+
+    /// Runs a single command, logged with its index in the input. Commands that succeed are
+    /// `debug` events, the ones the area rejects are `info` events carrying the error.
+    pub fn step(&mut self, index: usize, cmd: &Cmd) -> AResult<()> {
+        let res = match cmd.ty {
+            CmdType::Alloc => self.alloc(index, cmd.block_id.unwrap(), cmd.size.unwrap()),
+            CmdType::Dealloc => self.dealloc(cmd.block_id.unwrap()),
+            CmdType::Compact => self.area.compact(),
+            CmdType::Output => {
+                self.checkpoints.push(Checkpoint {
+                    index,
+                    area: self.area.clone(),
+                });
+                Ok(())
+            }
+        };
+        match &res {
+            Ok(_) => tracing::debug!(
+                method = self.method.name,
+                index,
+                cmd = ?cmd.ty,
+                block_id = cmd.block_id,
+                size = cmd.size,
+                outcome = "ok",
+            ),
+            Err(err) => tracing::info!(
+                method = self.method.name,
+                index,
+                cmd = ?cmd.ty,
+                block_id = cmd.block_id,
+                size = cmd.size,
+                outcome = "failed",
+                error = %err,
+            ),
+        }
+        res
+    }
+
+    fn alloc(&mut self, index: usize, block_id: u64, size: u64) -> AResult<()> {
+        match self.method.alloc(&mut self.area, block_id, size) {
+            Err(AError::Alloc(..))
+                if self.compaction == Compaction::OnFailure && self.area.compact().is_ok() =>
+            {
+                tracing::debug!(index, "compacted after a failed allocation");
+                self.method.alloc(&mut self.area, block_id, size)
             }
+            res => res,
+        }
+    }
+
+    fn dealloc(&mut self, block_id: u64) -> AResult<()> {
+        self.area.dealloc(block_id)?;
+        match self.compaction {
+            Compaction::AfterDealloc => self.area.compact(),
+            _ => Ok(()),
         }
-        println!("Fragmentation: {}.", self.area.fragmentation());
-        (self.area.clone(), self.method.clone())
     }
 }
 
 mod run_tests {
+    #[cfg(test)]
     use crate::cmd::CmdType;
 
     #[test]
     fn test_from_file() {
-        let run = super::Run::new("../input/test.txt", super::AllocationMethod::FirstFit).unwrap();
+        let run = super::Run::new("../input/test.txt", super::AllocationMethod::FIRST_FIT).unwrap();
         assert_eq!(run.area.size, 1000);
         assert_eq!(run.cmds.len(), 6);
 
@@ -265,9 +419,11 @@ D;2
 
         let (area, cmds) = super::Run::from_str(input).unwrap();
         let run = super::Run {
-            method: super::AllocationMethod::FirstFit,
+            method: super::AllocationMethod::FIRST_FIT,
             area,
-            cmds,
+            cmds: cmds.into(),
+            compaction: super::Compaction::Never,
+            checkpoints: vec![],
         };
         assert_eq!(run.area.size, 1000);
         assert_eq!(run.cmds.len(), 6);
@@ -300,4 +456,219 @@ D;2
         assert_eq!(cmd6.block_id, Some(2));
         assert_eq!(cmd6.ty, CmdType::Dealloc);
     }
+
+    #[test]
+    fn test_compaction() {
+        let input = "100 A;0;40 A;1;20 A;2;40 D;1 D;0 A;3;50";
+        let run = |compaction| {
+            let (area, cmds) = super::Run::from_str(input).unwrap();
+            let mut run = super::Run {
+                method: super::AllocationMethod::FIRST_FIT,
+                area,
+                cmds: cmds.into(),
+                compaction,
+                checkpoints: vec![],
+            };
+            run.run().area
+        };
+
+        let never = run(super::Compaction::Never);
+        assert_eq!(never.used_blocks.len(), 1);
+        assert_eq!(never.free_blocks.len(), 2);
+
+        let on_failure = run(super::Compaction::OnFailure);
+        assert_eq!(on_failure.used_blocks.len(), 2);
+        assert_eq!(on_failure.used_blocks[1].start_addr, 40);
+        assert_eq!(on_failure.free_blocks[0].size, 10);
+
+        let after_dealloc = run(super::Compaction::AfterDealloc);
+        assert_eq!(after_dealloc.used_blocks[0].start_addr, 0);
+        assert_eq!(after_dealloc.used_blocks[1].start_addr, 40);
+        assert_eq!(after_dealloc.free_blocks[0].size, 10);
+    }
+
+    #[test]
+    fn test_results_in_order() {
+        let (area, cmds) = super::Run::from_file("../input/test.txt").unwrap();
+        let cmds: std::sync::Arc<[crate::cmd::Cmd]> = cmds.into();
+        let methods = crate::method::Registry::default().all();
+        let mut simulation = super::Simulation::from_parts(
+            "test",
+            &area,
+            cmds.clone(),
+            &methods,
+            super::Compaction::Never,
+        );
+        assert!(simulation
+            .0
+            .iter()
+            .all(|r| std::sync::Arc::ptr_eq(&r.cmds, &cmds)));
+
+        let results = simulation.results();
+        assert_eq!(results.len(), methods.len());
+        for (outcome, expected) in results.iter().zip(methods.iter()) {
+            assert_eq!(outcome.method.name, expected.name);
+            let mut run = super::Run::new("../input/test.txt", *expected).unwrap();
+            let sequential = run.run().area;
+            assert_eq!(format!("{:?}", outcome.area), format!("{:?}", sequential));
+        }
+    }
+
+    #[test]
+    fn test_stream() {
+        let methods = [
+            super::AllocationMethod::FIRST_FIT,
+            super::AllocationMethod::BEST_FIT,
+        ];
+        let input = "1000\nA;0;100 A;1;100\nA;2;900\nD;1\n";
+        let mut out = vec![];
+        let results = super::stream(
+            input.as_bytes(),
+            &mut out,
+            &methods,
+            None,
+            Default::default(),
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), 2);
+        assert!(out.starts_with("First Fit;2;Unable to allocate 900 bytes for block 2."));
+
+        let (file_area, file_cmds) = super::Run::from_str(input).unwrap();
+        let mut file_run = super::Run {
+            method: methods[0],
+            area: file_area,
+            cmds: file_cmds.into(),
+            compaction: Default::default(),
+            checkpoints: vec![],
+        };
+        let area = file_run.run().area;
+        assert_eq!(format!("{:?}", results[0].area), format!("{:?}", area));
+
+        let mut out = vec![];
+        let results = super::stream(
+            "A;0;100".as_bytes(),
+            &mut out,
+            &methods,
+            Some(500),
+            Default::default(),
+        )
+        .unwrap();
+        assert_eq!(results[1].area.size, 500);
+        assert!(
+            super::stream("".as_bytes(), &mut out, &methods, None, Default::default()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_checkpoints() {
+        let input = "100\nA;0;10\nO\nA;1;20\nD;0\nO\n";
+        let (area, cmds) = super::Run::from_str(input).unwrap();
+        let mut simulation = super::Simulation::from_parts(
+            "test",
+            &area,
+            cmds.into(),
+            &[super::AllocationMethod::FIRST_FIT],
+            super::Compaction::Never,
+        );
+        let results = simulation.results();
+        let checkpoints = &results[0].checkpoints;
+        assert_eq!(checkpoints.len(), 2);
+        assert_eq!(checkpoints[0].index, 1);
+        assert_eq!(checkpoints[0].area.used_blocks.len(), 1);
+        assert_eq!(checkpoints[1].index, 4);
+        assert_eq!(checkpoints[1].area.used_blocks[0].start_addr, 10);
+
+        let mut out = vec![];
+        super::stream(
+            input.as_bytes(),
+            &mut out,
+            &[super::AllocationMethod::FIRST_FIT],
+            None,
+            Default::default(),
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("First Fit at command 1\nUsed Blocks:\n0;0;9\n"));
+        assert_eq!(out.matches(" at command ").count(), 2);
+
+        let err = super::Run::from_str("100\nA;0;10\nX;1\n").unwrap_err();
+        assert!(matches!(err, crate::cmd::ParseError::At(1, _)));
+    }
+
+    // Commands only a1rust models are skipped here when they leave the blocks alone, so the
+    // blocks both simulators end up with have to be the same. An empty allocation fails in both.
+    #[cfg(test)]
+    const SHARED_TRACE: &str = "1000\nA;0;100\nA;1;200\nW;1;7\nA;2;0\nD;0\nA;3;50\n\
+        T;3\nA;4;300;1\nL;3;4\nC\nA;5;150\nD;3\nA;6;40\n";
+
+    #[cfg(test)]
+    fn used_blocks(out: &str, algo: &str) -> Vec<String> {
+        let start = out.find(&format!("{}\nSize:\n", algo)).unwrap();
+        let section = out[start..].split("\n\n").next().unwrap();
+        let mut blocks: Vec<String> = section
+            .split("Allocated blocks:\n")
+            .nth(1)
+            .unwrap()
+            .lines()
+            .take_while(|l| !l.ends_with(':'))
+            .map(|l| l.to_owned())
+            .collect();
+        blocks.sort();
+        blocks
+    }
+
+    #[test]
+    fn test_shared_trace() {
+        let mut new = vec![];
+        a1rust::simulator::stream(
+            SHARED_TRACE.as_bytes(),
+            &mut new,
+            &a1rust::simulator::Options::default(),
+        )
+        .unwrap();
+        let new = String::from_utf8(new).unwrap();
+
+        let methods = [
+            super::AllocationMethod::FIRST_FIT,
+            super::AllocationMethod::BEST_FIT,
+            super::AllocationMethod::WORST_FIT,
+        ];
+        let mut old = vec![];
+        let results = super::stream(
+            SHARED_TRACE.as_bytes(),
+            &mut old,
+            &methods,
+            None,
+            Default::default(),
+        )
+        .unwrap();
+        let old = String::from_utf8(old).unwrap();
+
+        for (algo, outcome) in ["FirstFit", "BestFit", "WorstFit"]
+            .iter()
+            .zip(results.iter())
+        {
+            let mut blocks: Vec<String> = outcome
+                .area
+                .used_blocks
+                .iter()
+                .map(|b| b.to_string())
+                .collect();
+            blocks.sort();
+            assert_eq!(blocks, used_blocks(&new, algo), "{}", algo);
+            assert!(new.contains(&format!("{};Size;4;2\n", algo)));
+        }
+        assert_eq!(old.lines().count(), methods.len());
+        assert!(old.starts_with("First Fit;2;Unable to allocate block 2."));
+
+        // Killing process 1 frees block 4 in a1rust, here it would stay, so the trace is refused.
+        let err = super::Run::from_str("1000\nA;0;100\nA;4;300;1\nK;1\nA;5;150\n").unwrap_err();
+        match err {
+            crate::cmd::ParseError::At(2, err) => {
+                assert!(matches!(*err, crate::cmd::ParseError::Unsupported(_)))
+            }
+            err => panic!("{}", err),
+        }
+    }
 }